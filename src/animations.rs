@@ -0,0 +1,157 @@
+use crate::colors::ViewerColor;
+
+/// Largest scale reached by the pop of a pressed button
+pub const MAX_POP_SCALE: f64 = 2.;
+
+const fn default_glow_width() -> f64 {
+    6.
+}
+
+const fn default_pop_scale() -> f64 {
+    1.1
+}
+
+/// Progress curve of an animation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    #[default]
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the elapsed fraction of an animation, in `0.0..=1.0`, to its progress
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1. - (1. - t).powi(3),
+            Self::EaseInOut if t < 0.5 => 4. * t * t * t,
+            Self::EaseInOut => 1. - (-2. * t + 2.).powi(3) / 2.,
+        }
+    }
+}
+
+/// Duration and easing curve of an animation
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerAnimation {
+    /// In milliseconds
+    pub duration: u64,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl ViewerAnimation {
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.duration)
+    }
+
+    /// Returns the eased progress of the animation started at `started_at`, or `None` once it's over
+    pub fn progress(
+        &self,
+        started_at: Option<std::time::Instant>,
+        now: std::time::Instant,
+    ) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(started_at?);
+        if elapsed >= self.duration() {
+            return None;
+        }
+
+        Some(
+            self.easing
+                .apply(elapsed.as_secs_f32() / self.duration().as_secs_f32()),
+        )
+    }
+}
+
+/// Ring fading out around a button when it gets pressed
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerGlow {
+    /// In milliseconds
+    pub duration: u64,
+    #[serde(default)]
+    pub easing: Easing,
+    /// Thickness of the ring, in pixels before scaling
+    #[serde(default = "default_glow_width")]
+    pub width: f64,
+    /// Color of the ring, the active color of the button by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ViewerColor>,
+}
+
+/// Grows a button when it gets pressed, then shrinks it back to its size
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerPop {
+    /// In milliseconds
+    pub duration: u64,
+    #[serde(default)]
+    pub easing: Easing,
+    /// Scale of the button at the start of the pop
+    #[serde(default = "default_pop_scale")]
+    pub scale: f64,
+}
+
+impl ViewerGlow {
+    pub fn animation(&self) -> ViewerAnimation {
+        ViewerAnimation {
+            duration: self.duration,
+            easing: self.easing,
+        }
+    }
+}
+
+impl ViewerPop {
+    pub fn animation(&self) -> ViewerAnimation {
+        ViewerAnimation {
+            duration: self.duration,
+            easing: self.easing,
+        }
+    }
+}
+
+/// Transitions of the buttons between their active and inactive states. Buttons snap between them by default.
+///
+/// ```toml
+/// [animations.fade_out]
+/// duration = 150
+/// easing = "ease-out"
+///
+/// [animations.glow]
+/// duration = 200
+/// width = 6
+///
+/// [animations.pop]
+/// duration = 100
+/// scale = 1.1
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerAnimations {
+    /// Fades released buttons back to their inactive color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fade_out: Option<ViewerAnimation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glow: Option<ViewerGlow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pop: Option<ViewerPop>,
+}
+
+impl ViewerAnimations {
+    pub fn is_empty(&self) -> bool {
+        self.fade_out.is_none() && self.glow.is_none() && self.pop.is_none()
+    }
+
+    /// Every animation along with its name
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, ViewerAnimation)> {
+        [
+            ("fade_out", self.fade_out),
+            ("glow", self.glow.as_ref().map(ViewerGlow::animation)),
+            ("pop", self.pop.as_ref().map(ViewerPop::animation)),
+        ]
+        .into_iter()
+        .filter_map(|(name, animation)| Some((name, animation?)))
+    }
+}
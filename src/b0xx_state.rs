@@ -59,7 +59,129 @@ pub struct B0xxState {
     pub mod_ms: bool,
 }
 
-#[cfg(feature = "fake_inputs")]
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum B0xxButton {
+    Start,
+    Y,
+    X,
+    B,
+    A,
+    L,
+    R,
+    Z,
+    Up,
+    Down,
+    Right,
+    Left,
+    ModX,
+    ModY,
+    CLeft,
+    CRight,
+    CUp,
+    CDown,
+    ModLs,
+    ModMs,
+}
+
+impl B0xxButton {
+    /// All the buttons, in the order they are transmitted in a state report
+    pub const ALL: [B0xxButton; 20] = [
+        B0xxButton::Start,
+        B0xxButton::Y,
+        B0xxButton::X,
+        B0xxButton::B,
+        B0xxButton::A,
+        B0xxButton::L,
+        B0xxButton::R,
+        B0xxButton::Z,
+        B0xxButton::Up,
+        B0xxButton::Down,
+        B0xxButton::Right,
+        B0xxButton::Left,
+        B0xxButton::ModX,
+        B0xxButton::ModY,
+        B0xxButton::CLeft,
+        B0xxButton::CRight,
+        B0xxButton::CUp,
+        B0xxButton::CDown,
+        B0xxButton::ModLs,
+        B0xxButton::ModMs,
+    ];
+
+    /// Position of the button in `ALL`
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns true for the 2 extra buttons that aren't present on B0XX r1
+    pub const fn is_r2_only(self) -> bool {
+        matches!(self, B0xxButton::ModLs | B0xxButton::ModMs)
+    }
+}
+
+impl B0xxState {
+    pub fn is_pressed(&self, button: B0xxButton) -> bool {
+        match button {
+            B0xxButton::Start => self.start,
+            B0xxButton::Y => self.y,
+            B0xxButton::X => self.x,
+            B0xxButton::B => self.b,
+            B0xxButton::A => self.a,
+            B0xxButton::L => self.l,
+            B0xxButton::R => self.r,
+            B0xxButton::Z => self.z,
+            B0xxButton::Up => self.up,
+            B0xxButton::Down => self.down,
+            B0xxButton::Right => self.right,
+            B0xxButton::Left => self.left,
+            B0xxButton::ModX => self.mod_x,
+            B0xxButton::ModY => self.mod_y,
+            B0xxButton::CLeft => self.c_left,
+            B0xxButton::CRight => self.c_right,
+            B0xxButton::CUp => self.c_up,
+            B0xxButton::CDown => self.c_down,
+            B0xxButton::ModLs => self.mod_ls,
+            B0xxButton::ModMs => self.mod_ms,
+        }
+    }
+
+    /// Returns the buttons pressed in either state
+    pub fn union(&self, other: &Self) -> Self {
+        B0xxButton::ALL
+            .into_iter()
+            .filter(|button| self.is_pressed(*button) || other.is_pressed(*button))
+            .collect()
+    }
+
+    pub fn set_pressed(&mut self, button: B0xxButton, pressed: bool) {
+        match button {
+            B0xxButton::Start => self.start = pressed,
+            B0xxButton::Y => self.y = pressed,
+            B0xxButton::X => self.x = pressed,
+            B0xxButton::B => self.b = pressed,
+            B0xxButton::A => self.a = pressed,
+            B0xxButton::L => self.l = pressed,
+            B0xxButton::R => self.r = pressed,
+            B0xxButton::Z => self.z = pressed,
+            B0xxButton::Up => self.up = pressed,
+            B0xxButton::Down => self.down = pressed,
+            B0xxButton::Right => self.right = pressed,
+            B0xxButton::Left => self.left = pressed,
+            B0xxButton::ModX => self.mod_x = pressed,
+            B0xxButton::ModY => self.mod_y = pressed,
+            B0xxButton::CLeft => self.c_left = pressed,
+            B0xxButton::CRight => self.c_right = pressed,
+            B0xxButton::CUp => self.c_up = pressed,
+            B0xxButton::CDown => self.c_down = pressed,
+            B0xxButton::ModLs => self.mod_ls = pressed,
+            B0xxButton::ModMs => self.mod_ms = pressed,
+        }
+    }
+}
+
 impl B0xxState {
     pub fn random(rng: &mut rand::rngs::SmallRng) -> Self {
         use rand::RngCore as _;
@@ -90,6 +212,17 @@ impl B0xxState {
     }
 }
 
+/// Builds the state where only the given buttons are pressed
+impl FromIterator<B0xxButton> for B0xxState {
+    fn from_iter<I: IntoIterator<Item = B0xxButton>>(buttons: I) -> Self {
+        let mut state = Self::default();
+        for button in buttons {
+            state.set_pressed(button, true);
+        }
+        state
+    }
+}
+
 impl TryFrom<&[B0xxReport]> for B0xxState {
     type Error = crate::error::ViewerError;
 
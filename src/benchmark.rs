@@ -0,0 +1,266 @@
+use crate::latency::{LatencyMonitor, PERCENTILES, Samples, format_duration};
+
+/// Path of the report written by `--benchmark`, relative to the working directory
+pub const DEFAULT_BENCHMARK_REPORT: &str = "b0xx_viewer_benchmark.json";
+
+/// Measures the rendering performance over a fixed duration, for `--benchmark`
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    duration: std::time::Duration,
+    started_at: std::time::Instant,
+    /// Between two consecutive presented frames
+    frame_time: Samples,
+    /// CPU time spent in `render_gui`, for the frames it was called for
+    render_gui: Samples,
+    /// CPU time spent drawing and presenting every frame
+    draw: Samples,
+    last_frame_at: Option<std::time::Instant>,
+    wakeups: u64,
+    state_reports: u64,
+}
+
+impl Benchmark {
+    pub fn new(duration: std::time::Duration) -> Self {
+        Self {
+            duration,
+            started_at: std::time::Instant::now(),
+            frame_time: Samples::unbounded(),
+            render_gui: Samples::unbounded(),
+            draw: Samples::unbounded(),
+            last_frame_at: None,
+            wakeups: 0,
+            state_reports: 0,
+        }
+    }
+
+    pub fn ends_at(&self) -> std::time::Instant {
+        self.started_at + self.duration
+    }
+
+    pub fn is_over(&self) -> bool {
+        std::time::Instant::now() >= self.ends_at()
+    }
+
+    /// Counts a wake-up of the viewer by the serial thread, along with the state reports it picked up
+    pub fn woken_up(&mut self, state_reports: usize) {
+        self.wakeups += 1;
+        self.state_reports += state_reports as u64;
+    }
+
+    /// Records a presented frame, along with the CPU time spent building it, unless it was drawn again as it was, and drawing it
+    pub fn frame_presented(
+        &mut self,
+        presented_at: std::time::Instant,
+        render_gui: Option<std::time::Duration>,
+        draw: std::time::Duration,
+    ) {
+        if let Some(last_frame_at) = self.last_frame_at.replace(presented_at) {
+            self.frame_time
+                .push(presented_at.saturating_duration_since(last_frame_at));
+        }
+        if let Some(render_gui) = render_gui {
+            self.render_gui.push(render_gui);
+        }
+        self.draw.push(draw);
+    }
+
+    /// Summarizes the measurements, along with the latency measured in the meantime
+    pub fn report(&self, device: Option<String>, latency: &LatencyMonitor) -> BenchmarkReport {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        BenchmarkReport {
+            version: env!("CARGO_PKG_VERSION"),
+            date: chrono::Local::now().to_rfc3339(),
+            os: std::env::consts::OS,
+            device,
+            duration_secs: elapsed,
+            frames: self.draw.count(),
+            fps: self.draw.count() as f64 / elapsed,
+            frame_time: DurationStats::new(&self.frame_time),
+            render_gui: DurationStats::new(&self.render_gui),
+            draw: DurationStats::new(&self.draw),
+            latency: DurationStats::new(&latency.latency),
+            channel: ChannelStats {
+                wakeups: self.wakeups,
+                wakeups_per_sec: self.wakeups as f64 / elapsed,
+                state_reports: self.state_reports,
+                state_reports_per_sec: self.state_reports as f64 / elapsed,
+            },
+        }
+    }
+}
+
+/// Measures the CPU time spent by the current thread, or the wall-clock time on platforms that can't tell
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTimer {
+    started_at: std::time::Instant,
+    cpu_started_at: Option<std::time::Duration>,
+}
+
+impl CpuTimer {
+    pub fn start() -> Self {
+        Self {
+            started_at: std::time::Instant::now(),
+            cpu_started_at: thread_cpu_time(),
+        }
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        match self.cpu_started_at.zip(thread_cpu_time()) {
+            Some((started_at, now)) => now.saturating_sub(started_at),
+            None => self.started_at.elapsed(),
+        }
+    }
+}
+
+/// CPU time consumed by the current thread so far
+#[cfg(unix)]
+fn thread_cpu_time() -> Option<std::time::Duration> {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `time` is a valid `timespec` for the call to write to
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    (result == 0).then(|| std::time::Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+/// CPU time consumed by the current thread so far, in user and kernel mode
+#[cfg(windows)]
+fn thread_cpu_time() -> Option<std::time::Duration> {
+    use winapi::shared::minwindef::FILETIME;
+    use winapi::um::processthreadsapi::{GetCurrentThread, GetThreadTimes};
+
+    let empty = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+    // SAFETY: the pseudo handle of the current thread is always valid, and the times are valid `FILETIME`s to write to
+    let result = unsafe {
+        GetThreadTimes(
+            GetCurrentThread(),
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        )
+    };
+
+    // In units of 100 nanoseconds
+    let ticks = |time: FILETIME| {
+        (u64::from(time.dwHighDateTime) << 32 | u64::from(time.dwLowDateTime)) * 100
+    };
+    (result != 0).then(|| std::time::Duration::from_nanos(ticks(kernel) + ticks(user)))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn thread_cpu_time() -> Option<std::time::Duration> {
+    None
+}
+
+/// Results of a benchmark, written as JSON so that they can be compared between releases
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BenchmarkReport {
+    pub version: &'static str,
+    pub date: String,
+    pub os: &'static str,
+    /// Port of the B0XX, or name of the fake inputs that were played
+    pub device: Option<String>,
+    pub duration_secs: f64,
+    pub frames: usize,
+    pub fps: f64,
+    pub frame_time: DurationStats,
+    /// CPU time spent in `render_gui` per frame
+    pub render_gui: DurationStats,
+    /// CPU time spent drawing and presenting every frame
+    pub draw: DurationStats,
+    pub latency: DurationStats,
+    pub channel: ChannelStats,
+}
+
+/// Distribution of durations, in milliseconds
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct DurationStats {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl DurationStats {
+    fn new(samples: &Samples) -> Self {
+        let (Some(mean), Some([p50, p90, p95, p99]), Some(max)) = (
+            samples.mean(),
+            samples.percentiles(PERCENTILES),
+            samples.max(),
+        ) else {
+            return Self::default();
+        };
+
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.;
+        Self {
+            samples: samples.count(),
+            mean_ms: ms(mean),
+            p50_ms: ms(p50),
+            p90_ms: ms(p90),
+            p95_ms: ms(p95),
+            p99_ms: ms(p99),
+            max_ms: ms(max),
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "mean {} · p50 {} · p99 {} · max {}",
+            format_ms(self.mean_ms),
+            format_ms(self.p50_ms),
+            format_ms(self.p99_ms),
+            format_ms(self.max_ms),
+        )
+    }
+}
+
+fn format_ms(ms: f64) -> String {
+    format_duration(std::time::Duration::from_secs_f64(ms / 1000.))
+}
+
+/// Reports passed from the serial thread to the viewer
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChannelStats {
+    /// Times the viewer was woken up to pick the reports up
+    pub wakeups: u64,
+    pub wakeups_per_sec: f64,
+    pub state_reports: u64,
+    pub state_reports_per_sec: f64,
+}
+
+impl BenchmarkReport {
+    /// Writes the report as pretty-printed JSON
+    pub fn write(&self, path: &std::path::Path) -> crate::ViewerResult<()> {
+        let json = serde_json::to_string_pretty(self).map_err(anyhow::Error::from)?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{} frames in {:.1}s ({:.1} FPS)",
+            self.frames, self.duration_secs, self.fps
+        );
+        for (name, stats) in [
+            ("Frame time", &self.frame_time),
+            ("render_gui", &self.render_gui),
+            ("Draw", &self.draw),
+            ("Latency", &self.latency),
+        ] {
+            println!("{name}: {}", stats.summary());
+        }
+        println!(
+            "Channel: {:.0} state reports/s, picked up in {:.0} wake-ups/s",
+            self.channel.state_reports_per_sec, self.channel.wakeups_per_sec
+        );
+    }
+}
@@ -1,8 +1,16 @@
+use crate::b0xx_state::B0xxButton;
 use conrod_core::Color;
 
-pub const DEFAULT_ACTIVE_COLOR: ViewerColor = ViewerColor(rgb::RGB8::new(0, 235, 255));
-pub const DEFAULT_INACTIVE_COLOR: ViewerColor = ViewerColor(rgb::RGB8::new(85, 87, 83));
-pub const DEFAULT_BACKGROUND_COLOR: ViewerColor = ViewerColor(rgb::RGB8::new(19, 19, 19));
+use crate::error::ViewerOptionConfigError;
+
+pub const DEFAULT_ACTIVE_COLOR: ViewerColor =
+    ViewerColor(rgb::RGBA8::new(0, 235, 255, 255), ColorNotation::Hex);
+pub const DEFAULT_INACTIVE_COLOR: ViewerColor =
+    ViewerColor(rgb::RGBA8::new(85, 87, 83, 255), ColorNotation::Hex);
+pub const DEFAULT_BACKGROUND_COLOR: ViewerColor =
+    ViewerColor(rgb::RGBA8::new(19, 19, 19, 255), ColorNotation::Hex);
+pub const DEFAULT_SHADOW_COLOR: ViewerColor =
+    ViewerColor(rgb::RGBA8::new(0, 0, 0, 128), ColorNotation::Hex);
 
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code)]
@@ -12,17 +20,73 @@ pub enum ViewerColorType {
     Background,
 }
 
-#[derive(
-    Debug, Clone, Copy, serde_with::SerializeDisplay, serde_with::DeserializeFromStr, PartialEq, Eq,
-)]
+/// Notation a color was written in, so that it's written back the same way
+#[derive(Debug, Clone, Copy, Default)]
+enum ColorNotation {
+    /// `#RGB` or `#RGBA`
+    ShortHex,
+    /// `#RRGGBB` or `#RRGGBBAA`
+    #[default]
+    Hex,
+    /// `rgb(r, g, b)` or `rgba(r, g, b, alpha)`
+    Rgb { alpha: Option<Alpha> },
+    /// `hsl(h, s%, l%)` or `hsla(h, s%, l%, alpha)`
+    Hsl {
+        hue: f32,
+        saturation: f32,
+        lightness: f32,
+        alpha: Option<Alpha>,
+    },
+    /// CSS color name
+    Named(&'static str),
+}
 
-pub struct ViewerColor(rgb::RGB8);
+/// Alpha channel of a `rgba()` or `hsla()` color, as it was written
+#[derive(Debug, Clone, Copy)]
+enum Alpha {
+    /// A number between 0 and 1, eg. `0.5`
+    Number(f32),
+    /// A percentage, eg. `50%`
+    Percentage(f32),
+}
 
-impl ViewerColor {
-    pub const fn active_default() -> Self {
-        DEFAULT_ACTIVE_COLOR
+impl Alpha {
+    /// Opacity between 0 and 1
+    fn value(self) -> f32 {
+        match self {
+            Alpha::Number(value) => value,
+            Alpha::Percentage(percentage) => percentage / 100.,
+        }
     }
+}
 
+impl std::fmt::Display for Alpha {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alpha::Number(value) => write!(f, "{value}"),
+            Alpha::Percentage(percentage) => write!(f, "{percentage}%"),
+        }
+    }
+}
+
+/// Color of the viewer. It can be written in any of these notations:
+/// * Hex: `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`
+/// * RGB: `rgb(0, 235, 255)` or `rgba(0, 235, 255, 0.5)`
+/// * HSL: `hsl(186, 100%, 50%)` or `hsla(186, 100%, 50%, 0.5)`
+/// * CSS color names, eg. `rebeccapurple`
+#[derive(Debug, Clone, Copy, serde_with::SerializeDisplay, serde_with::DeserializeFromStr)]
+pub struct ViewerColor(rgb::RGBA8, ColorNotation);
+
+/// Colors are equal if they look the same, whatever notation they are written in
+impl PartialEq for ViewerColor {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for ViewerColor {}
+
+impl ViewerColor {
     pub const fn inactive_default() -> Self {
         DEFAULT_INACTIVE_COLOR
     }
@@ -30,6 +94,25 @@ impl ViewerColor {
     pub const fn background_default() -> Self {
         DEFAULT_BACKGROUND_COLOR
     }
+
+    pub const fn shadow_default() -> Self {
+        DEFAULT_SHADOW_COLOR
+    }
+
+    /// Linearly interpolates between this color and `to`, `t` being in `0.0..=1.0`
+    pub fn lerp(self, to: Self, t: f32) -> Self {
+        let t = t.clamp(0., 1.);
+        let lerp = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        Self(
+            rgb::RGBA8::new(
+                lerp(self.0.r, to.0.r),
+                lerp(self.0.g, to.0.g),
+                lerp(self.0.b, to.0.b),
+                lerp(self.0.a, to.0.a),
+            ),
+            ColorNotation::Hex,
+        )
+    }
 }
 
 impl Default for ViewerColor {
@@ -40,13 +123,14 @@ impl Default for ViewerColor {
 
 impl From<Color> for ViewerColor {
     fn from(value: Color) -> Self {
-        let (r, g, b) = (
+        let (r, g, b, a) = (
             (value.red() * 255.).ceil() as u8,
             (value.green() * 255.).ceil() as u8,
             (value.blue() * 255.).ceil() as u8,
+            (value.to_rgb().3 * 255.).ceil() as u8,
         );
 
-        ViewerColor(rgb::RGB8::new(r, g, b))
+        ViewerColor(rgb::RGBA8::new(r, g, b, a), ColorNotation::Hex)
     }
 }
 
@@ -56,35 +140,239 @@ impl Into<Color> for ViewerColor {
             self.0.r as f32 / 255.,
             self.0.g as f32 / 255.,
             self.0.b as f32 / 255.,
-            1.,
+            self.0.a as f32 / 255.,
         )
     }
 }
 
 impl std::str::FromStr for ViewerColor {
-    type Err = crate::error::ViewerOptionConfigError;
-
-    fn from_str(s: &str) -> Result<Self, crate::error::ViewerOptionConfigError> {
-        let color_value = u32::from_str_radix(s.trim_start_matches('#'), 16)?;
-        let (r, g, b) = (
-            ((color_value >> 16) & 255) as u8,
-            ((color_value >> 8) & 255) as u8,
-            (color_value & 255) as u8,
-        );
+    type Err = ViewerOptionConfigError;
+
+    fn from_str(s: &str) -> Result<Self, ViewerOptionConfigError> {
+        let color = s.trim();
+        let invalid = |reason: String| ViewerOptionConfigError::InvalidColor {
+            color: color.to_string(),
+            reason,
+        };
+
+        if color.is_empty() {
+            return Err(invalid("the color is empty".into()));
+        }
+
+        if let Some(hex) = color.strip_prefix('#') {
+            return parse_hex(hex).map_err(invalid);
+        }
 
-        Ok(Self(rgb::RGB8::new(r, g, b)))
+        // Hex colors used to be accepted without their leading `#`
+        if color.chars().all(|c| c.is_ascii_hexdigit()) {
+            return parse_hex(color).map_err(invalid);
+        }
+
+        if let Some((function, args)) = color.split_once('(') {
+            let args = args
+                .strip_suffix(')')
+                .ok_or_else(|| invalid("missing closing parenthesis".into()))?;
+            return parse_function(&function.trim().to_ascii_lowercase(), args).map_err(invalid);
+        }
+
+        let name = color.to_ascii_lowercase();
+        NAMED_COLORS
+            .binary_search_by_key(&name.as_str(), |(name, _)| name)
+            .map(|i| {
+                let (name, color) = NAMED_COLORS[i];
+                Self(color, ColorNotation::Named(name))
+            })
+            .map_err(|_| {
+                invalid(
+                    "unknown color, expected a hex color such as \"#00EBFF\", rgb(), rgba(), hsl(), hsla() or a CSS color name"
+                        .into(),
+                )
+            })
+    }
+}
+
+/// Parses the digits of a `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA` color
+fn parse_hex(hex: &str) -> Result<ViewerColor, String> {
+    let digits = hex
+        .chars()
+        .map(|c| {
+            c.to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or_else(|| format!("`{c}` is not a hexadecimal digit"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (components, notation): (Vec<u8>, _) = match digits.len() {
+        3 | 4 => (
+            digits.iter().map(|digit| digit * 17).collect(),
+            ColorNotation::ShortHex,
+        ),
+        6 | 8 => (
+            digits
+                .chunks(2)
+                .map(|pair| pair[0] << 4 | pair[1])
+                .collect(),
+            ColorNotation::Hex,
+        ),
+        len => {
+            return Err(format!(
+                "expected 3 (#RGB), 4 (#RGBA), 6 (#RRGGBB) or 8 (#RRGGBBAA) hexadecimal digits, got {len}"
+            ));
+        }
+    };
+
+    let a = components.get(3).copied().unwrap_or(255);
+    Ok(ViewerColor(
+        rgb::RGBA8::new(components[0], components[1], components[2], a),
+        notation,
+    ))
+}
+
+/// Parses the arguments of a `rgb()`, `rgba()`, `hsl()` or `hsla()` color
+fn parse_function(function: &str, args: &str) -> Result<ViewerColor, String> {
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    let ([x, y, z], alpha) = match args[..] {
+        [x, y, z] => ([x, y, z], None),
+        [x, y, z, alpha] => ([x, y, z], Some(parse_alpha(alpha)?)),
+        _ => {
+            return Err(format!(
+                "{function}() expects 3 or 4 comma-separated components, got {}",
+                args.len()
+            ));
+        }
+    };
+
+    let a = alpha.map_or(255, |alpha| (alpha.value() * 255.).round() as u8);
+    match function {
+        "rgb" | "rgba" => {
+            let channel = |name: &str, value: &str| {
+                value.parse::<u8>().map_err(|_| {
+                    format!("{name} must be an integer between 0 and 255, got `{value}`")
+                })
+            };
+
+            Ok(ViewerColor(
+                rgb::RGBA8::new(
+                    channel("red", x)?,
+                    channel("green", y)?,
+                    channel("blue", z)?,
+                    a,
+                ),
+                ColorNotation::Rgb { alpha },
+            ))
+        }
+        "hsl" | "hsla" => {
+            let hue = parse_number(x.strip_suffix("deg").unwrap_or(x))
+                .ok_or_else(|| format!("hue must be a number of degrees, got `{x}`"))?;
+            let percentage = |name: &str, value: &str| {
+                parse_number(value.strip_suffix('%').unwrap_or(value))
+                    .filter(|value| (0.0..=100.).contains(value))
+                    .ok_or_else(|| {
+                        format!("{name} must be a percentage between 0% and 100%, got `{value}`")
+                    })
+            };
+            let saturation = percentage("saturation", y)?;
+            let lightness = percentage("lightness", z)?;
+
+            let rgb::RGB8 { r, g, b } = hsl_to_rgb(hue, saturation / 100., lightness / 100.);
+            Ok(ViewerColor(
+                rgb::RGBA8::new(r, g, b, a),
+                ColorNotation::Hsl {
+                    hue,
+                    saturation,
+                    lightness,
+                    alpha,
+                },
+            ))
+        }
+        _ => Err(format!(
+            "unknown color function `{function}()`, expected rgb(), rgba(), hsl() or hsla()"
+        )),
     }
 }
 
+/// Parses an alpha channel, either a number between 0 and 1 or a percentage
+fn parse_alpha(alpha: &str) -> Result<Alpha, String> {
+    let value = match alpha.strip_suffix('%') {
+        Some(percentage) => parse_number(percentage).map(Alpha::Percentage),
+        None => parse_number(alpha).map(Alpha::Number),
+    };
+
+    value
+        .filter(|value| (0.0..=1.).contains(&value.value()))
+        .ok_or_else(|| {
+            format!("alpha must be a number between 0 and 1, or a percentage, got `{alpha}`")
+        })
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// Converts a color from HSL, with the saturation and lightness in `0.0..=1.0`
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> rgb::RGB8 {
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let sector = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (sector % 2. - 1.).abs());
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    let m = lightness - chroma / 2.;
+    let channel = |value: f32| ((value + m) * 255.).round().clamp(0., 255.) as u8;
+    rgb::RGB8::new(channel(r), channel(g), channel(b))
+}
+
 impl std::fmt::Display for ViewerColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let rgb::RGB8 { r, g, b } = self.0;
-        write!(f, "#{r:02X}{g:02X}{b:02X}")
+        let rgb::RGBA8 { r, g, b, a } = self.0;
+        match self.1 {
+            // Short hex can only be kept if every component has two identical digits
+            ColorNotation::ShortHex if [r, g, b, a].iter().all(|c| c % 17 == 0) => {
+                write!(f, "#{:X}{:X}{:X}", r / 17, g / 17, b / 17)?;
+                if a < 255 {
+                    write!(f, "{:X}", a / 17)?;
+                }
+
+                Ok(())
+            }
+            ColorNotation::ShortHex | ColorNotation::Hex => {
+                write!(f, "#{r:02X}{g:02X}{b:02X}")?;
+                if a < 255 {
+                    write!(f, "{a:02X}")?;
+                }
+
+                Ok(())
+            }
+            ColorNotation::Rgb { alpha: None } => write!(f, "rgb({r}, {g}, {b})"),
+            ColorNotation::Rgb { alpha: Some(alpha) } => {
+                write!(f, "rgba({r}, {g}, {b}, {alpha})")
+            }
+            ColorNotation::Hsl {
+                hue,
+                saturation,
+                lightness,
+                alpha,
+            } => match alpha {
+                None => write!(f, "hsl({hue}, {saturation}%, {lightness}%)"),
+                Some(alpha) => write!(f, "hsla({hue}, {saturation}%, {lightness}%, {alpha})"),
+            },
+            ColorNotation::Named(name) => write!(f, "{name}"),
+        }
     }
 }
 
 impl std::ops::Deref for ViewerColor {
-    type Target = rgb::RGB8;
+    type Target = rgb::RGBA8;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -168,67 +456,351 @@ impl ViewerButtonColors {
         }
     }
 
-    pub fn merge_defaults(&mut self, other: ViewerColor) {
-        let default_color = ViewerColor::default();
-        if self.start == default_color {
-            self.start = other;
-        }
-        if self.y == default_color {
-            self.y = other;
-        }
-        if self.x == default_color {
-            self.x = other;
-        }
-        if self.b == default_color {
-            self.b = other;
-        }
-        if self.a == default_color {
-            self.a = other;
-        }
-        if self.l == default_color {
-            self.l = other;
-        }
-        if self.r == default_color {
-            self.r = other;
-        }
-        if self.z == default_color {
-            self.z = other;
-        }
-        if self.up == default_color {
-            self.up = other;
-        }
-        if self.down == default_color {
-            self.down = other;
-        }
-        if self.right == default_color {
-            self.right = other;
-        }
-        if self.left == default_color {
-            self.left = other;
-        }
-        if self.mod_x == default_color {
-            self.mod_x = other;
-        }
-        if self.mod_y == default_color {
-            self.mod_y = other;
-        }
-        if self.c_left == default_color {
-            self.c_left = other;
+    pub fn get(&self, button: B0xxButton) -> ViewerColor {
+        match button {
+            B0xxButton::Start => self.start,
+            B0xxButton::Y => self.y,
+            B0xxButton::X => self.x,
+            B0xxButton::B => self.b,
+            B0xxButton::A => self.a,
+            B0xxButton::L => self.l,
+            B0xxButton::R => self.r,
+            B0xxButton::Z => self.z,
+            B0xxButton::Up => self.up,
+            B0xxButton::Down => self.down,
+            B0xxButton::Right => self.right,
+            B0xxButton::Left => self.left,
+            B0xxButton::ModX => self.mod_x,
+            B0xxButton::ModY => self.mod_y,
+            B0xxButton::CLeft => self.c_left,
+            B0xxButton::CRight => self.c_right,
+            B0xxButton::CUp => self.c_up,
+            B0xxButton::CDown => self.c_down,
+            B0xxButton::ModLs => self.mod_ls,
+            B0xxButton::ModMs => self.mod_ms,
         }
-        if self.c_right == default_color {
-            self.c_right = other;
-        }
-        if self.c_up == default_color {
-            self.c_up = other;
+    }
+
+    pub fn get_mut(&mut self, button: B0xxButton) -> &mut ViewerColor {
+        match button {
+            B0xxButton::Start => &mut self.start,
+            B0xxButton::Y => &mut self.y,
+            B0xxButton::X => &mut self.x,
+            B0xxButton::B => &mut self.b,
+            B0xxButton::A => &mut self.a,
+            B0xxButton::L => &mut self.l,
+            B0xxButton::R => &mut self.r,
+            B0xxButton::Z => &mut self.z,
+            B0xxButton::Up => &mut self.up,
+            B0xxButton::Down => &mut self.down,
+            B0xxButton::Right => &mut self.right,
+            B0xxButton::Left => &mut self.left,
+            B0xxButton::ModX => &mut self.mod_x,
+            B0xxButton::ModY => &mut self.mod_y,
+            B0xxButton::CLeft => &mut self.c_left,
+            B0xxButton::CRight => &mut self.c_right,
+            B0xxButton::CUp => &mut self.c_up,
+            B0xxButton::CDown => &mut self.c_down,
+            B0xxButton::ModLs => &mut self.mod_ls,
+            B0xxButton::ModMs => &mut self.mod_ms,
         }
-        if self.c_down == default_color {
-            self.c_down = other;
+    }
+
+    /// Linearly interpolates every button color towards the ones of `to`
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        let mut colors = *self;
+        for button in B0xxButton::ALL {
+            *colors.get_mut(button) = self.get(button).lerp(to.get(button), t);
         }
-        if self.mod_ls == default_color {
-            self.mod_ls = other;
+        colors
+    }
+}
+
+/// CSS named colors, sorted by name
+const NAMED_COLORS: [(&str, rgb::RGBA8); 149] = [
+    ("aliceblue", rgb::RGBA8::new(240, 248, 255, 255)),
+    ("antiquewhite", rgb::RGBA8::new(250, 235, 215, 255)),
+    ("aqua", rgb::RGBA8::new(0, 255, 255, 255)),
+    ("aquamarine", rgb::RGBA8::new(127, 255, 212, 255)),
+    ("azure", rgb::RGBA8::new(240, 255, 255, 255)),
+    ("beige", rgb::RGBA8::new(245, 245, 220, 255)),
+    ("bisque", rgb::RGBA8::new(255, 228, 196, 255)),
+    ("black", rgb::RGBA8::new(0, 0, 0, 255)),
+    ("blanchedalmond", rgb::RGBA8::new(255, 235, 205, 255)),
+    ("blue", rgb::RGBA8::new(0, 0, 255, 255)),
+    ("blueviolet", rgb::RGBA8::new(138, 43, 226, 255)),
+    ("brown", rgb::RGBA8::new(165, 42, 42, 255)),
+    ("burlywood", rgb::RGBA8::new(222, 184, 135, 255)),
+    ("cadetblue", rgb::RGBA8::new(95, 158, 160, 255)),
+    ("chartreuse", rgb::RGBA8::new(127, 255, 0, 255)),
+    ("chocolate", rgb::RGBA8::new(210, 105, 30, 255)),
+    ("coral", rgb::RGBA8::new(255, 127, 80, 255)),
+    ("cornflowerblue", rgb::RGBA8::new(100, 149, 237, 255)),
+    ("cornsilk", rgb::RGBA8::new(255, 248, 220, 255)),
+    ("crimson", rgb::RGBA8::new(220, 20, 60, 255)),
+    ("cyan", rgb::RGBA8::new(0, 255, 255, 255)),
+    ("darkblue", rgb::RGBA8::new(0, 0, 139, 255)),
+    ("darkcyan", rgb::RGBA8::new(0, 139, 139, 255)),
+    ("darkgoldenrod", rgb::RGBA8::new(184, 134, 11, 255)),
+    ("darkgray", rgb::RGBA8::new(169, 169, 169, 255)),
+    ("darkgreen", rgb::RGBA8::new(0, 100, 0, 255)),
+    ("darkgrey", rgb::RGBA8::new(169, 169, 169, 255)),
+    ("darkkhaki", rgb::RGBA8::new(189, 183, 107, 255)),
+    ("darkmagenta", rgb::RGBA8::new(139, 0, 139, 255)),
+    ("darkolivegreen", rgb::RGBA8::new(85, 107, 47, 255)),
+    ("darkorange", rgb::RGBA8::new(255, 140, 0, 255)),
+    ("darkorchid", rgb::RGBA8::new(153, 50, 204, 255)),
+    ("darkred", rgb::RGBA8::new(139, 0, 0, 255)),
+    ("darksalmon", rgb::RGBA8::new(233, 150, 122, 255)),
+    ("darkseagreen", rgb::RGBA8::new(143, 188, 143, 255)),
+    ("darkslateblue", rgb::RGBA8::new(72, 61, 139, 255)),
+    ("darkslategray", rgb::RGBA8::new(47, 79, 79, 255)),
+    ("darkslategrey", rgb::RGBA8::new(47, 79, 79, 255)),
+    ("darkturquoise", rgb::RGBA8::new(0, 206, 209, 255)),
+    ("darkviolet", rgb::RGBA8::new(148, 0, 211, 255)),
+    ("deeppink", rgb::RGBA8::new(255, 20, 147, 255)),
+    ("deepskyblue", rgb::RGBA8::new(0, 191, 255, 255)),
+    ("dimgray", rgb::RGBA8::new(105, 105, 105, 255)),
+    ("dimgrey", rgb::RGBA8::new(105, 105, 105, 255)),
+    ("dodgerblue", rgb::RGBA8::new(30, 144, 255, 255)),
+    ("firebrick", rgb::RGBA8::new(178, 34, 34, 255)),
+    ("floralwhite", rgb::RGBA8::new(255, 250, 240, 255)),
+    ("forestgreen", rgb::RGBA8::new(34, 139, 34, 255)),
+    ("fuchsia", rgb::RGBA8::new(255, 0, 255, 255)),
+    ("gainsboro", rgb::RGBA8::new(220, 220, 220, 255)),
+    ("ghostwhite", rgb::RGBA8::new(248, 248, 255, 255)),
+    ("gold", rgb::RGBA8::new(255, 215, 0, 255)),
+    ("goldenrod", rgb::RGBA8::new(218, 165, 32, 255)),
+    ("gray", rgb::RGBA8::new(128, 128, 128, 255)),
+    ("green", rgb::RGBA8::new(0, 128, 0, 255)),
+    ("greenyellow", rgb::RGBA8::new(173, 255, 47, 255)),
+    ("grey", rgb::RGBA8::new(128, 128, 128, 255)),
+    ("honeydew", rgb::RGBA8::new(240, 255, 240, 255)),
+    ("hotpink", rgb::RGBA8::new(255, 105, 180, 255)),
+    ("indianred", rgb::RGBA8::new(205, 92, 92, 255)),
+    ("indigo", rgb::RGBA8::new(75, 0, 130, 255)),
+    ("ivory", rgb::RGBA8::new(255, 255, 240, 255)),
+    ("khaki", rgb::RGBA8::new(240, 230, 140, 255)),
+    ("lavender", rgb::RGBA8::new(230, 230, 250, 255)),
+    ("lavenderblush", rgb::RGBA8::new(255, 240, 245, 255)),
+    ("lawngreen", rgb::RGBA8::new(124, 252, 0, 255)),
+    ("lemonchiffon", rgb::RGBA8::new(255, 250, 205, 255)),
+    ("lightblue", rgb::RGBA8::new(173, 216, 230, 255)),
+    ("lightcoral", rgb::RGBA8::new(240, 128, 128, 255)),
+    ("lightcyan", rgb::RGBA8::new(224, 255, 255, 255)),
+    ("lightgoldenrodyellow", rgb::RGBA8::new(250, 250, 210, 255)),
+    ("lightgray", rgb::RGBA8::new(211, 211, 211, 255)),
+    ("lightgreen", rgb::RGBA8::new(144, 238, 144, 255)),
+    ("lightgrey", rgb::RGBA8::new(211, 211, 211, 255)),
+    ("lightpink", rgb::RGBA8::new(255, 182, 193, 255)),
+    ("lightsalmon", rgb::RGBA8::new(255, 160, 122, 255)),
+    ("lightseagreen", rgb::RGBA8::new(32, 178, 170, 255)),
+    ("lightskyblue", rgb::RGBA8::new(135, 206, 250, 255)),
+    ("lightslategray", rgb::RGBA8::new(119, 136, 153, 255)),
+    ("lightslategrey", rgb::RGBA8::new(119, 136, 153, 255)),
+    ("lightsteelblue", rgb::RGBA8::new(176, 196, 222, 255)),
+    ("lightyellow", rgb::RGBA8::new(255, 255, 224, 255)),
+    ("lime", rgb::RGBA8::new(0, 255, 0, 255)),
+    ("limegreen", rgb::RGBA8::new(50, 205, 50, 255)),
+    ("linen", rgb::RGBA8::new(250, 240, 230, 255)),
+    ("magenta", rgb::RGBA8::new(255, 0, 255, 255)),
+    ("maroon", rgb::RGBA8::new(128, 0, 0, 255)),
+    ("mediumaquamarine", rgb::RGBA8::new(102, 205, 170, 255)),
+    ("mediumblue", rgb::RGBA8::new(0, 0, 205, 255)),
+    ("mediumorchid", rgb::RGBA8::new(186, 85, 211, 255)),
+    ("mediumpurple", rgb::RGBA8::new(147, 112, 219, 255)),
+    ("mediumseagreen", rgb::RGBA8::new(60, 179, 113, 255)),
+    ("mediumslateblue", rgb::RGBA8::new(123, 104, 238, 255)),
+    ("mediumspringgreen", rgb::RGBA8::new(0, 250, 154, 255)),
+    ("mediumturquoise", rgb::RGBA8::new(72, 209, 204, 255)),
+    ("mediumvioletred", rgb::RGBA8::new(199, 21, 133, 255)),
+    ("midnightblue", rgb::RGBA8::new(25, 25, 112, 255)),
+    ("mintcream", rgb::RGBA8::new(245, 255, 250, 255)),
+    ("mistyrose", rgb::RGBA8::new(255, 228, 225, 255)),
+    ("moccasin", rgb::RGBA8::new(255, 228, 181, 255)),
+    ("navajowhite", rgb::RGBA8::new(255, 222, 173, 255)),
+    ("navy", rgb::RGBA8::new(0, 0, 128, 255)),
+    ("oldlace", rgb::RGBA8::new(253, 245, 230, 255)),
+    ("olive", rgb::RGBA8::new(128, 128, 0, 255)),
+    ("olivedrab", rgb::RGBA8::new(107, 142, 35, 255)),
+    ("orange", rgb::RGBA8::new(255, 165, 0, 255)),
+    ("orangered", rgb::RGBA8::new(255, 69, 0, 255)),
+    ("orchid", rgb::RGBA8::new(218, 112, 214, 255)),
+    ("palegoldenrod", rgb::RGBA8::new(238, 232, 170, 255)),
+    ("palegreen", rgb::RGBA8::new(152, 251, 152, 255)),
+    ("paleturquoise", rgb::RGBA8::new(175, 238, 238, 255)),
+    ("palevioletred", rgb::RGBA8::new(219, 112, 147, 255)),
+    ("papayawhip", rgb::RGBA8::new(255, 239, 213, 255)),
+    ("peachpuff", rgb::RGBA8::new(255, 218, 185, 255)),
+    ("peru", rgb::RGBA8::new(205, 133, 63, 255)),
+    ("pink", rgb::RGBA8::new(255, 192, 203, 255)),
+    ("plum", rgb::RGBA8::new(221, 160, 221, 255)),
+    ("powderblue", rgb::RGBA8::new(176, 224, 230, 255)),
+    ("purple", rgb::RGBA8::new(128, 0, 128, 255)),
+    ("rebeccapurple", rgb::RGBA8::new(102, 51, 153, 255)),
+    ("red", rgb::RGBA8::new(255, 0, 0, 255)),
+    ("rosybrown", rgb::RGBA8::new(188, 143, 143, 255)),
+    ("royalblue", rgb::RGBA8::new(65, 105, 225, 255)),
+    ("saddlebrown", rgb::RGBA8::new(139, 69, 19, 255)),
+    ("salmon", rgb::RGBA8::new(250, 128, 114, 255)),
+    ("sandybrown", rgb::RGBA8::new(244, 164, 96, 255)),
+    ("seagreen", rgb::RGBA8::new(46, 139, 87, 255)),
+    ("seashell", rgb::RGBA8::new(255, 245, 238, 255)),
+    ("sienna", rgb::RGBA8::new(160, 82, 45, 255)),
+    ("silver", rgb::RGBA8::new(192, 192, 192, 255)),
+    ("skyblue", rgb::RGBA8::new(135, 206, 235, 255)),
+    ("slateblue", rgb::RGBA8::new(106, 90, 205, 255)),
+    ("slategray", rgb::RGBA8::new(112, 128, 144, 255)),
+    ("slategrey", rgb::RGBA8::new(112, 128, 144, 255)),
+    ("snow", rgb::RGBA8::new(255, 250, 250, 255)),
+    ("springgreen", rgb::RGBA8::new(0, 255, 127, 255)),
+    ("steelblue", rgb::RGBA8::new(70, 130, 180, 255)),
+    ("tan", rgb::RGBA8::new(210, 180, 140, 255)),
+    ("teal", rgb::RGBA8::new(0, 128, 128, 255)),
+    ("thistle", rgb::RGBA8::new(216, 191, 216, 255)),
+    ("tomato", rgb::RGBA8::new(255, 99, 71, 255)),
+    ("transparent", rgb::RGBA8::new(0, 0, 0, 0)),
+    ("turquoise", rgb::RGBA8::new(64, 224, 208, 255)),
+    ("violet", rgb::RGBA8::new(238, 130, 238, 255)),
+    ("wheat", rgb::RGBA8::new(245, 222, 179, 255)),
+    ("white", rgb::RGBA8::new(255, 255, 255, 255)),
+    ("whitesmoke", rgb::RGBA8::new(245, 245, 245, 255)),
+    ("yellow", rgb::RGBA8::new(255, 255, 0, 255)),
+    ("yellowgreen", rgb::RGBA8::new(154, 205, 50, 255)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(color: &str) -> (u8, u8, u8, u8) {
+        let rgb::RGBA8 { r, g, b, a } = color.parse::<ViewerColor>().unwrap().0;
+        (r, g, b, a)
+    }
+
+    fn error(color: &str) -> String {
+        color.parse::<ViewerColor>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(rgba("#00EBFF"), (0, 235, 255, 255));
+        assert_eq!(rgba("#00ebff80"), (0, 235, 255, 128));
+        assert_eq!(rgba("#0EF"), (0, 238, 255, 255));
+        assert_eq!(rgba("#0EF8"), (0, 238, 255, 136));
+        assert_eq!(rgba(" 00EBFF "), (0, 235, 255, 255));
+    }
+
+    #[test]
+    fn rejects_invalid_hex_colors() {
+        assert_eq!(
+            error("#00EBF"),
+            "Invalid color `#00EBF`: expected 3 (#RGB), 4 (#RGBA), 6 (#RRGGBB) or 8 (#RRGGBBAA) hexadecimal digits, got 5"
+        );
+        assert_eq!(
+            error("#00EBFG"),
+            "Invalid color `#00EBFG`: `G` is not a hexadecimal digit"
+        );
+        assert_eq!(error(""), "Invalid color ``: the color is empty");
+    }
+
+    #[test]
+    fn parses_rgb_colors() {
+        assert_eq!(rgba("rgb(0, 235, 255)"), (0, 235, 255, 255));
+        assert_eq!(rgba("RGBA(0,235,255,0.5)"), (0, 235, 255, 128));
+        assert_eq!(rgba("rgba(0, 235, 255, 25%)"), (0, 235, 255, 64));
+        assert_eq!(rgba("rgba(255, 255, 255, 0)"), (255, 255, 255, 0));
+        assert_eq!(rgba("rgba(0, 0, 0, 1)"), (0, 0, 0, 255));
+    }
+
+    #[test]
+    fn rejects_invalid_rgb_colors() {
+        assert_eq!(
+            error("rgb(0, 256, 0)"),
+            "Invalid color `rgb(0, 256, 0)`: green must be an integer between 0 and 255, got `256`"
+        );
+        assert_eq!(
+            error("rgb(0, 0)"),
+            "Invalid color `rgb(0, 0)`: rgb() expects 3 or 4 comma-separated components, got 2"
+        );
+        assert_eq!(
+            error("rgba(0, 0, 0, 1.5)"),
+            "Invalid color `rgba(0, 0, 0, 1.5)`: alpha must be a number between 0 and 1, or a percentage, got `1.5`"
+        );
+        assert_eq!(
+            error("rgb(0, 0, 0"),
+            "Invalid color `rgb(0, 0, 0`: missing closing parenthesis"
+        );
+        assert_eq!(
+            error("cmyk(0, 0, 0, 0)"),
+            "Invalid color `cmyk(0, 0, 0, 0)`: unknown color function `cmyk()`, expected rgb(), rgba(), hsl() or hsla()"
+        );
+    }
+
+    #[test]
+    fn parses_hsl_colors() {
+        assert_eq!(rgba("hsl(0, 100%, 50%)"), (255, 0, 0, 255));
+        assert_eq!(rgba("hsl(120deg, 100%, 25%)"), (0, 128, 0, 255));
+        assert_eq!(rgba("hsl(-120, 100%, 50%)"), (0, 0, 255, 255));
+        assert_eq!(rgba("hsl(0, 0%, 100%)"), (255, 255, 255, 255));
+        assert_eq!(rgba("hsla(360, 100%, 50%, 0.5)"), (255, 0, 0, 128));
+        assert_eq!(rgba("hsla(360, 100%, 50%, 50%)"), (255, 0, 0, 128));
+    }
+
+    #[test]
+    fn rejects_invalid_hsl_colors() {
+        assert_eq!(
+            error("hsl(red, 100%, 50%)"),
+            "Invalid color `hsl(red, 100%, 50%)`: hue must be a number of degrees, got `red`"
+        );
+        assert_eq!(
+            error("hsl(0, 101%, 50%)"),
+            "Invalid color `hsl(0, 101%, 50%)`: saturation must be a percentage between 0% and 100%, got `101%`"
+        );
+        assert_eq!(
+            error("hsl(0, 100%, -1%)"),
+            "Invalid color `hsl(0, 100%, -1%)`: lightness must be a percentage between 0% and 100%, got `-1%`"
+        );
+    }
+
+    #[test]
+    fn parses_named_colors() {
+        assert_eq!(rgba("red"), (255, 0, 0, 255));
+        assert_eq!(rgba("RebeccaPurple"), (102, 51, 153, 255));
+        assert!(error("notacolor").starts_with("Invalid color `notacolor`: unknown color"));
+    }
+
+    #[test]
+    fn keeps_the_notation_of_colors() {
+        for color in [
+            "#00EBFF",
+            "#00EBFF80",
+            "#0EF",
+            "#0EF8",
+            "rgb(0, 235, 255)",
+            "rgba(0, 235, 255, 0.5)",
+            "rgba(0, 235, 255, 50%)",
+            "hsl(120, 100%, 25%)",
+            "hsla(120, 100%, 25%, 0.5)",
+            "hsla(120, 100%, 25%, 12.5%)",
+            "red",
+        ] {
+            assert_eq!(color.parse::<ViewerColor>().unwrap().to_string(), color);
         }
-        if self.mod_ms == default_color {
-            self.mod_ms = other;
+
+        assert_eq!(rgba("#FFFFFFFF"), rgba("white"));
+        assert_eq!(
+            "#ffffff".parse::<ViewerColor>().unwrap().to_string(),
+            "#FFFFFF"
+        );
+    }
+
+    #[test]
+    fn converts_to_and_from_conrod_colors() {
+        for color in ["#00EBFF", "#00EBFF80", "#00000000"] {
+            let color = color.parse::<ViewerColor>().unwrap();
+            let conrod: Color = color.into();
+            assert_eq!(ViewerColor::from(conrod), color);
         }
     }
 }
@@ -1,123 +1,721 @@
+use crate::animations::ViewerAnimations;
+use crate::benchmark::DEFAULT_BENCHMARK_REPORT;
 use crate::colors::*;
-use crate::{ViewerOptionConfigError, ViewerResult};
+use crate::diagnostics::Severity;
+use crate::fake_inputs::{FakeInputs, FakeInputsMode};
+use crate::labels::{LabelSet, ViewerButtonLabels};
+use crate::layers::{
+    ConfigLayer, LayeredConfig, absolutize_paths, env_layers, find_config, local_config_paths,
+    system_config_path, user_config_paths,
+};
+use crate::layout::ViewerLayout;
+use crate::logger::{
+    DEFAULT_LOG_MAX_FILES, DEFAULT_LOG_MAX_SIZE, LogFile, LogFormat, LogLevel, LogOptions,
+};
+use crate::migrations::CONFIG_VERSION;
+use crate::profile::ViewerProfile;
+use crate::skin::ViewerSkin;
+use crate::themes::BundledTheme;
+use crate::ui::{WIN_H, WIN_W};
+use crate::web_theme::ImportedTheme;
+use crate::{ViewerError, ViewerOptionConfigError, ViewerResult};
 
 pub const DEFAULT_FILENAME: &str = "b0xx_viewer_config.toml";
+/// Interval at which the configuration file is checked for changes
+const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
 
-fn hex_to_color(s: &str) -> Result<ViewerColor, ViewerOptionConfigError> {
+fn parse_color(s: &str) -> Result<ViewerColor, ViewerOptionConfigError> {
     use std::str::FromStr as _;
     ViewerColor::from_str(s)
 }
 
-fn hex_to_button_colors(s: &str) -> Result<ViewerButtonColors, ViewerOptionConfigError> {
-    let color = hex_to_color(s)?;
+fn parse_button_colors(s: &str) -> Result<ViewerButtonColors, ViewerOptionConfigError> {
+    let color = parse_color(s)?;
     Ok(ViewerButtonColors::new_with_color(color))
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, clap::Parser, serde::Serialize, serde::Deserialize)]
+const fn default_version() -> i64 {
+    CONFIG_VERSION
+}
+
+const fn default_window_width() -> u32 {
+    WIN_W
+}
+
+const fn default_window_height() -> u32 {
+    WIN_H
+}
+
+const fn default_scale() -> f64 {
+    1.
+}
+
+const fn default_vsync() -> bool {
+    true
+}
+
+const fn default_msaa() -> u16 {
+    DEFAULT_MSAA
+}
+
+const fn default_label_font_size() -> u32 {
+    DEFAULT_LABEL_FONT_SIZE
+}
+
+const fn default_log_max_size() -> u64 {
+    DEFAULT_LOG_MAX_SIZE
+}
+
+const fn default_log_max_files() -> u32 {
+    DEFAULT_LOG_MAX_FILES
+}
+
+pub const DEFAULT_LABEL_FONT_SIZE: u32 = 18;
+/// Number of samples per pixel used to smooth the edges of the buttons by default
+pub const DEFAULT_MSAA: u16 = 4;
+pub const MAX_MSAA: u16 = 16;
+
+/// Checks that a number of samples per pixel is 0 or a power of two up to `MAX_MSAA`
+pub fn is_valid_msaa(samples: u16) -> bool {
+    samples == 0 || (samples.is_power_of_two() && samples <= MAX_MSAA)
+}
+
+fn parse_msaa(s: &str) -> Result<u16, String> {
+    let samples = s.parse().map_err(|e| format!("{e}"))?;
+    if !is_valid_msaa(samples) {
+        return Err(format!("must be 0 or a power of two up to {MAX_MSAA}"));
+    }
+    Ok(samples)
+}
+
+/// Vertical alignment of the labels inside of their button
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, PartialEq, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[command(author, version, about, long_about = None)]
 pub struct ViewerOptions {
     /// Path of the current configuration. Used for caching purposes when saving/loading configurations
     #[serde(skip)]
     #[arg(skip)]
     path: std::path::PathBuf,
-    /// Intializes an empty configuration in the executable's folder
-    #[arg(long, exclusive(true))]
+    /// Configuration files of every layer, watched for changes
+    #[serde(skip)]
+    #[arg(skip)]
+    layer_files: Vec<std::path::PathBuf>,
+    /// Command line the configuration was resolved with, reused when it's reloaded
+    #[serde(skip)]
+    #[arg(skip)]
+    matches: clap::ArgMatches,
+    /// Intializes a default configuration in the user configuration directory, or at the --config path
+    #[arg(long)]
     #[serde(skip)]
     pub init_config: bool,
+    /// Checks the configuration file, reporting every problem found along with its location, then exits
+    #[arg(long)]
+    #[serde(skip)]
+    pub check_config: bool,
+    /// Prints the effective value of every setting, along with where it was set, then exits
+    #[arg(long)]
+    #[serde(skip)]
+    pub print_config: bool,
+    /// Upgrades the configuration file to the current format, keeping a backup of it, then exits
+    #[arg(long)]
+    #[serde(skip)]
+    pub migrate_config: bool,
+    /// Converts a theme stylesheet of the official web input viewer into a new configuration file, then exits
+    #[arg(long, value_name = "CSS_FILE")]
+    #[serde(skip)]
+    pub import_web_theme: Option<std::path::PathBuf>,
+    /// Lists the bundled themes, then exits
+    #[arg(long)]
+    #[serde(skip)]
+    pub list_themes: bool,
+    /// Bundled theme used as the base of the configuration, see --list-themes
+    #[arg(long)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Configuration file or bundled theme that this configuration overrides. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Error that prevented the configuration file from loading, displayed on screen
+    #[serde(skip)]
+    #[arg(skip)]
+    pub config_error: Option<String>,
+    /// Version of the configuration format, used to upgrade older configuration files
+    #[arg(skip = CONFIG_VERSION)]
+    #[serde(default = "default_version")]
+    pub version: i64,
     /// Enable button labels
-    #[arg(long = "labels", short)]
+    #[arg(long = "labels", short, overrides_with = "no_display_labels")]
     #[serde(default)]
     pub display_labels: bool,
-    /// Makes the window chromeless (i.e. removes window decorations such as titlebar, minimize/close buttons etc)
+    /// Disables button labels, overriding the configuration
+    #[arg(long = "no-labels", overrides_with = "display_labels")]
+    #[serde(skip)]
+    no_display_labels: bool,
+    /// Sets the built-in set of labels to display. It can be cycled through with the L key
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(default)]
+    pub label_set: LabelSet,
+    /// Custom labels, overriding the ones of the label set. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "ViewerButtonLabels::is_empty")]
+    pub labels: ViewerButtonLabels,
+    /// Loads a custom TTF/OTF font for the labels. Labels with glyphs missing from it use the embedded font
     #[arg(long)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<std::path::PathBuf>,
+    /// Sets the font size of the labels, before scaling
+    #[arg(long, default_value_t = DEFAULT_LABEL_FONT_SIZE)]
+    #[serde(default = "default_label_font_size")]
+    pub label_font_size: u32,
+    /// Overrides the color of the labels, eg. "#FFFFFF" or "white". Labels contrast with their button by default
+    #[arg(long, value_parser = parse_color)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_color: Option<ViewerColor>,
+    /// Sets the vertical alignment of the labels inside of their button
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(default)]
+    pub label_align: LabelAlign,
+    /// Makes the window chromeless (i.e. removes window decorations such as titlebar, minimize/close buttons etc)
+    #[arg(long, overrides_with = "no_chromeless")]
     #[serde(default)]
     pub chromeless: bool,
+    /// Keeps the window decorations, overriding the configuration
+    #[arg(long = "no-chromeless", overrides_with = "chromeless")]
+    #[serde(skip)]
+    no_chromeless: bool,
+    /// Makes the window background transparent, following the alpha channel of the background color
+    #[arg(long, overrides_with = "no_transparent")]
+    #[serde(default)]
+    pub transparent: bool,
+    /// Keeps the window background opaque, overriding the configuration
+    #[arg(long = "no-transparent", overrides_with = "transparent")]
+    #[serde(skip)]
+    no_transparent: bool,
     /// Enables B0XX r1 mode to remove the 2 extra buttons
-    #[arg(long = "r1")]
+    #[arg(long = "r1", overrides_with = "no_is_r1_b0xx")]
     #[serde(default)]
     pub is_r1_b0xx: bool,
+    /// Disables B0XX r1 mode, overriding the configuration
+    #[arg(long = "no-r1", overrides_with = "is_r1_b0xx")]
+    #[serde(skip)]
+    no_is_r1_b0xx: bool,
     /// Enables an alternative mode of inactive button coloring; Makes inactive button background neutral in favor of button rims instead.
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_colored_rims")]
     #[serde(default)]
     pub colored_rims: bool,
+    /// Disables the alternative mode of inactive button coloring, overriding the configuration
+    #[arg(long = "no-colored-rims", overrides_with = "colored_rims")]
+    #[serde(skip)]
+    no_colored_rims: bool,
+    /// Shows the port, identity, uptime and report rate of the B0XX. It can be toggled with the I key
+    #[arg(long, overrides_with = "no_connection_info")]
+    #[serde(default)]
+    pub connection_info: bool,
+    /// Hides the connection info, overriding the configuration
+    #[arg(long = "no-connection-info", overrides_with = "connection_info")]
+    #[serde(skip)]
+    no_connection_info: bool,
+    /// Shows histograms of the latency added by the viewer and of the interval between reports. They can be toggled with the H key
+    #[arg(long, overrides_with = "no_latency_histogram")]
+    #[serde(default)]
+    pub latency_histogram: bool,
+    /// Hides the latency histograms, overriding the configuration
+    #[arg(long = "no-latency-histogram", overrides_with = "latency_histogram")]
+    #[serde(skip)]
+    no_latency_histogram: bool,
+    /// Measures the latency for SECONDS, then prints its percentiles and exits
+    #[arg(long, value_name = "SECONDS")]
+    #[serde(skip)]
+    pub measure_latency: Option<u64>,
+    /// Renders as fast as possible for SECONDS, then writes a performance report and exits. Plays random fake inputs unless --fake-inputs is given
+    #[arg(long, value_name = "SECONDS")]
+    #[serde(skip)]
+    pub benchmark: Option<u64>,
+    /// Path of the JSON report written by --benchmark
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_BENCHMARK_REPORT)]
+    #[serde(skip)]
+    pub benchmark_report: std::path::PathBuf,
+    /// Plays fake inputs instead of connecting to a B0XX, for demos and tests
+    #[arg(long, value_enum, value_name = "MODE")]
+    #[serde(skip)]
+    pub fake_inputs: Option<FakeInputsMode>,
+    /// Seeds the random and realistic fake inputs, to reproduce them. A new seed is picked and logged otherwise
+    #[arg(long, value_name = "SEED")]
+    #[serde(skip)]
+    pub fake_inputs_seed: Option<u64>,
+    /// Script played by `--fake-inputs script`, see the "Fake inputs" section of the README
+    #[arg(long, value_name = "FILE", required_if_eq("fake_inputs", "script"))]
+    #[serde(skip)]
+    pub fake_inputs_script: Option<std::path::PathBuf>,
+    /// Sets the initial width of the window, in logical pixels
+    #[arg(long = "width", default_value_t = WIN_W)]
+    #[serde(default = "default_window_width")]
+    pub window_width: u32,
+    /// Sets the initial height of the window, in logical pixels
+    #[arg(long = "height", default_value_t = WIN_H)]
+    #[serde(default = "default_window_height")]
+    pub window_height: u32,
+    /// Scales the initial window size, eg. "1.5". The layout and fonts follow the window size
+    #[arg(long, default_value_t = default_scale())]
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Keeps the initial aspect ratio of the window when it gets resized
+    #[arg(long, overrides_with = "no_lock_aspect_ratio")]
+    #[serde(default)]
+    pub lock_aspect_ratio: bool,
+    /// Lets the window be resized freely, overriding the configuration
+    #[arg(long = "no-lock-aspect-ratio", overrides_with = "lock_aspect_ratio")]
+    #[serde(skip)]
+    no_lock_aspect_ratio: bool,
+    /// Synchronizes the frames with the refresh rate of the display, which is the default
+    #[arg(long, overrides_with = "no_vsync")]
+    #[serde(default = "default_vsync")]
+    pub vsync: bool,
+    /// Presents the frames as soon as they are drawn, lowering the latency at the cost of tearing
+    #[arg(long = "no-vsync", overrides_with = "vsync")]
+    #[serde(skip)]
+    no_vsync: bool,
+    /// Caps the number of frames drawn per second. Changes happening in between are shown on the next frame
+    #[arg(long, value_name = "FPS")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fps: Option<u32>,
+    /// Sets the number of samples per pixel smoothing the edges of the buttons: 0 (disabled), 2, 4, 8 or 16
+    #[arg(long, value_name = "SAMPLES", value_parser = parse_msaa, default_value_t = DEFAULT_MSAA)]
+    #[serde(default = "default_msaa")]
+    pub msaa: u16,
+    /// Only redraws when the buttons, the window or the connection change. The connection info and histograms are refreshed along with them, and profiles switch without crossfading
+    #[arg(long, overrides_with = "no_low_power")]
+    #[serde(default)]
+    pub low_power: bool,
+    /// Disables the low-power mode, overriding the configuration
+    #[arg(long = "no-low-power", overrides_with = "low_power")]
+    #[serde(skip)]
+    no_low_power: bool,
     /// Relaxes B0XX detection to allow any 16MHz Arduino-compatible device to connect
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_relax_arduino_detection")]
     #[serde(default)]
     pub relax_arduino_detection: bool,
+    /// Only allows B0XX devices to connect, overriding the configuration
+    #[arg(
+        long = "no-relax-arduino-detection",
+        overrides_with = "relax_arduino_detection"
+    )]
+    #[serde(skip)]
+    no_relax_arduino_detection: bool,
     /// Sets the configuration file path
     #[arg(long = "config", short = 'c')]
     #[serde(skip)]
     pub config_path: Option<std::path::PathBuf>,
-    /// Sets a custom background color in hex format, eg. "#00FF00"
-    #[arg(long = "background", short = 'b', value_parser = hex_to_color, default_value = "#131313")]
+    /// Sets a custom background color, eg. "#00FF00", "#00FF0080" with an alpha channel, "rgb(0, 255, 0)", "hsl(120, 100%, 50%)" or "lime"
+    #[arg(long = "background", short = 'b', value_parser = parse_color, default_value = "#131313")]
     #[serde(default = "ViewerColor::background_default")]
     pub background_color: ViewerColor,
-    /// Sets a custom color for pressed/active buttons in hex format, eg. "#00FF00"
-    #[arg(long = "active", short = 'a', value_parser = hex_to_button_colors, default_value = "#00EBFF")]
+    /// Sets a custom color for pressed/active buttons, eg. "#00FF00" or any other color notation
+    #[arg(long = "active", short = 'a', value_parser = parse_button_colors, default_value = "#00EBFF")]
     #[serde(default = "ViewerButtonColors::default_active")]
     pub button_active_colors: ViewerButtonColors,
-    /// Sets a custom color for inactive buttons in hex format, eg. "#00FF00"
-    #[arg(long = "inactive", short = 'i', value_parser = hex_to_button_colors, default_value = "#555753")]
+    /// Sets a custom color for inactive buttons, eg. "#00FF00" or any other color notation
+    #[arg(long = "inactive", short = 'i', value_parser = parse_button_colors, default_value = "#555753")]
     #[serde(default = "ViewerButtonColors::default_inactive")]
     pub button_inactive_colors: ViewerButtonColors,
+    /// Keeps buttons shown as pressed for at least MS milliseconds, so that taps of a frame or two show up on a stream. 0 disables it
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    #[serde(default)]
+    pub min_press_duration: u64,
+    /// Sets the color of released buttons still shown as pressed because of --min-press-duration, eg. "#FFFFFF". They keep their active color by default
+    #[arg(long, value_parser = parse_color)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tap_color: Option<ViewerColor>,
+    /// Button layout, overriding the default positions of the buttons. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "ViewerLayout::is_empty")]
+    pub layout: ViewerLayout,
+    /// Selects the profile to start with. Profiles can be cycled through with the P key
+    #[arg(long = "profile")]
+    #[serde(rename = "default_profile", skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Duration of the crossfade between profiles when switching them, in milliseconds. 0 disables it
+    #[arg(long, default_value_t = 0)]
+    #[serde(default)]
+    pub profile_crossfade: u64,
+    /// Named profiles overriding the appearance settings. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub profiles: std::collections::BTreeMap<String, ViewerProfile>,
+    /// Image-based skin for the background and buttons. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "ViewerSkin::is_empty")]
+    pub skin: ViewerSkin,
+    /// Transitions of the buttons when they get pressed and released. Only available in configuration files
+    #[arg(skip)]
+    #[serde(default, skip_serializing_if = "ViewerAnimations::is_empty")]
+    pub animations: ViewerAnimations,
     /// Provide a custom COM port (Windows-only) or a /dev/ttyXXX path (Unix). Bypasses auto-detection, so proceed at your own risk!
     #[arg(long = "tty")]
     #[serde(rename = "tty")]
     pub custom_tty: Option<String>,
+    /// Sets the verbosity of the log
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// Sets the format of the log, both on stdout and in the log file
+    #[arg(long, value_enum, default_value_t)]
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Log file, next to the executable by default. Set with --log-file and --no-log-file on the command line
+    #[arg(skip)]
+    #[serde(default)]
+    pub log_file: LogFile,
+    /// Writes the log file at PATH instead of next to the executable
+    #[arg(long = "log-file", value_name = "PATH", overrides_with = "no_log_file")]
+    #[serde(skip)]
+    log_file_path: Option<std::path::PathBuf>,
+    /// Disables the log file, only logging to stdout
+    #[arg(long = "no-log-file", overrides_with = "log_file_path")]
+    #[serde(skip)]
+    no_log_file: bool,
+    /// Size from which the log file is rotated, in kilobytes. 0 disables the rotation
+    #[arg(long, value_name = "KB", default_value_t = DEFAULT_LOG_MAX_SIZE)]
+    #[serde(default = "default_log_max_size")]
+    pub log_max_size: u64,
+    /// Number of rotated log files kept along with the current one
+    #[arg(long, default_value_t = DEFAULT_LOG_MAX_FILES)]
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: u32,
 }
 
 impl Default for ViewerOptions {
     fn default() -> Self {
         Self {
             init_config: false,
+            check_config: false,
+            print_config: false,
+            migrate_config: false,
+            import_web_theme: None,
+            list_themes: false,
+            theme: None,
+            extends: None,
+            version: CONFIG_VERSION,
+            config_error: None,
             relax_arduino_detection: false,
+            no_relax_arduino_detection: false,
             display_labels: false,
+            no_display_labels: false,
+            label_set: LabelSet::default(),
+            labels: ViewerButtonLabels::default(),
+            font: None,
+            label_font_size: default_label_font_size(),
+            label_color: None,
+            label_align: LabelAlign::default(),
             chromeless: false,
+            no_chromeless: false,
+            transparent: false,
+            no_transparent: false,
             background_color: DEFAULT_BACKGROUND_COLOR,
             button_inactive_colors: ViewerButtonColors::new_with_color(DEFAULT_INACTIVE_COLOR),
             button_active_colors: ViewerButtonColors::new_with_color(DEFAULT_ACTIVE_COLOR),
+            min_press_duration: 0,
+            tap_color: None,
+            layout: ViewerLayout::default(),
+            profile: None,
+            profile_crossfade: 0,
+            profiles: Default::default(),
+            skin: ViewerSkin::default(),
+            animations: ViewerAnimations::default(),
             custom_tty: None,
+            log_level: LogLevel::default(),
+            log_format: LogFormat::default(),
+            log_file: LogFile::default(),
+            log_file_path: None,
+            no_log_file: false,
+            log_max_size: DEFAULT_LOG_MAX_SIZE,
+            log_max_files: DEFAULT_LOG_MAX_FILES,
             is_r1_b0xx: false,
+            no_is_r1_b0xx: false,
             colored_rims: false,
+            no_colored_rims: false,
+            connection_info: false,
+            no_connection_info: false,
+            latency_histogram: false,
+            no_latency_histogram: false,
+            measure_latency: None,
+            benchmark: None,
+            benchmark_report: DEFAULT_BENCHMARK_REPORT.into(),
+            fake_inputs: None,
+            fake_inputs_seed: None,
+            fake_inputs_script: None,
+            window_width: default_window_width(),
+            window_height: default_window_height(),
+            scale: default_scale(),
+            lock_aspect_ratio: false,
+            no_lock_aspect_ratio: false,
+            vsync: default_vsync(),
+            no_vsync: false,
+            max_fps: None,
+            msaa: DEFAULT_MSAA,
+            low_power: false,
+            no_low_power: false,
             config_path: None,
             path: Default::default(),
+            layer_files: vec![],
+            matches: Default::default(),
         }
     }
 }
 
 impl ViewerOptions {
-    fn get_cwd() -> ViewerResult<std::path::PathBuf> {
-        let mut path = std::env::current_exe()?;
-        path.set_file_name(DEFAULT_FILENAME);
-        Ok(path)
+    /// Returns the initial logical size of the window, with the scale factor applied
+    pub fn initial_window_size(&self) -> (f64, f64) {
+        let scale = if self.scale > 0. { self.scale } else { 1. };
+        (
+            self.window_width.max(1) as f64 * scale,
+            self.window_height.max(1) as f64 * scale,
+        )
     }
 
-    /// Loads a configuration at `path`.
-    /// If `path` isn't provided, it will default to the current working directory of the executable.
-    pub fn load_config(path: Option<&std::path::PathBuf>) -> ViewerResult<Self> {
-        let path = if let Some(path) = path.cloned().take() {
-            path
-        } else {
-            Self::get_cwd()?
+    /// Returns the shortest interval between two frames, if the frame rate is capped
+    pub fn min_frame_interval(&self) -> Option<std::time::Duration> {
+        let max_fps = self.max_fps.filter(|max_fps| *max_fps > 0)?;
+        Some(std::time::Duration::from_secs(1) / max_fps)
+    }
+
+    /// Folder of the loaded configuration file, which relative paths are resolved against
+    pub fn config_dir(&self) -> Option<&std::path::Path> {
+        self.path.parent()
+    }
+
+    /// Resolves a path from the configuration relative to the configuration file
+    pub fn resolve_path(&self, path: &std::path::Path) -> std::path::PathBuf {
+        match self.config_dir() {
+            Some(config_dir) => config_dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Returns the logging settings
+    pub fn log_options(&self) -> LogOptions {
+        LogOptions {
+            level: self.log_level,
+            file: self.log_file.clone(),
+            format: self.log_format,
+            max_size: self.log_max_size,
+            max_files: self.log_max_files,
+        }
+    }
+
+    /// Returns the fake inputs to play instead of connecting to a B0XX, if any.
+    /// Benchmarks play random ones by default, with a fixed seed and as fast as possible, so that their results can be compared.
+    pub fn fake_inputs(&self) -> ViewerResult<Option<FakeInputs>> {
+        let is_benchmark = self.benchmark.is_some();
+        let Some(mode) = self
+            .fake_inputs
+            .or(is_benchmark.then_some(FakeInputsMode::Random))
+        else {
+            return Ok(None);
+        };
+
+        let seed = self.fake_inputs_seed.or(is_benchmark.then_some(0));
+        let fake_inputs = FakeInputs::new(mode, seed, self.fake_inputs_script.as_deref())?;
+        Ok(Some(fake_inputs.throttled(!is_benchmark)))
+    }
+
+    /// Returns these options with the settings of the profile `name` applied
+    pub fn with_profile(&self, name: Option<&str>) -> Self {
+        let mut options = self.clone();
+        if let Some(profile) = name.and_then(|name| self.profiles.get(name)) {
+            profile.apply(&mut options);
+        }
+        options
+    }
+
+    /// Returns the name of the profile following `current`, wrapping around
+    pub fn next_profile(&self, current: Option<&str>) -> Option<&str> {
+        let mut names = self.profiles.keys().map(String::as_str);
+        let first = names.clone().next();
+        current
+            .and_then(|current| names.find(|name| *name > current))
+            .or(first)
+    }
+
+    pub fn min_press_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.min_press_duration)
+    }
+
+    /// Returns these options with their colors blended towards the ones of `to`, `t` being in `0.0..=1.0`
+    pub fn blend(&self, to: &Self, t: f32) -> Self {
+        let mut options = to.clone();
+        options.background_color = self.background_color.lerp(to.background_color, t);
+        options.button_active_colors = self.button_active_colors.lerp(&to.button_active_colors, t);
+        options.button_inactive_colors = self
+            .button_inactive_colors
+            .lerp(&to.button_inactive_colors, t);
+        options
+    }
+
+    /// Path of the configuration file of the current user, where new configurations are saved by default
+    fn user_config_path() -> ViewerResult<std::path::PathBuf> {
+        user_config_paths().into_iter().next().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Could not find the user configuration directory, provide a path with --config"
+            )
+            .into()
+        })
+    }
+
+    /// Path of the configuration file that isn't merged from another layer: the `--config` file,
+    /// or the first configuration found next to the executable, in the working directory, then in the user configuration directory
+    fn discovered_config_path(
+        config_path: Option<&std::path::Path>,
+    ) -> ViewerResult<std::path::PathBuf> {
+        if let Some(path) = config_path {
+            return Ok(path.to_path_buf());
+        }
+
+        let paths = [local_config_paths(), user_config_paths()].concat();
+        find_config(&paths)
+            .cloned()
+            .ok_or_else(|| ViewerOptionConfigError::NotFound.into())
+    }
+
+    /// Upgrades the configuration read from `path` to the current format if it's an older one
+    fn migrate_source(path: &std::path::Path, source: &str) -> ViewerResult<String> {
+        // Syntax errors are left to the diagnostics
+        let Ok(mut document) = source.parse::<toml_edit::DocumentMut>() else {
+            return Ok(source.to_string());
         };
 
+        match crate::migrations::migrate(&mut document)? {
+            Some(version) => {
+                log::warn!(
+                    "{} uses the configuration format version {version}, run with --migrate-config to upgrade it",
+                    path.display()
+                );
+                Ok(document.to_string())
+            }
+            None => Ok(source.to_string()),
+        }
+    }
+
+    /// Loads the configuration file at `path` as a layer, with its paths made absolute.
+    /// Returns `None` if there's no file at `path`.
+    fn load_layer(path: &std::path::Path) -> ViewerResult<Option<toml::Table>> {
         if !path.exists() {
-            return Err(ViewerOptionConfigError::NotFound.into());
+            return Ok(None);
         }
 
-        let str_buf = std::fs::read_to_string(&path)?;
-        let mut ret: ViewerOptions =
+        let source = std::fs::read_to_string(path)?;
+        let str_buf = Self::migrate_source(path, &source)?;
+        // The diagnostics point at the file as it was written, rather than at its upgraded version
+        let diagnostics = crate::diagnostics::check_config(path, &source);
+        for diagnostic in &diagnostics {
+            match diagnostic.severity {
+                Severity::Warning => log::warn!("{diagnostic}"),
+                Severity::Error => log::error!("{diagnostic}"),
+            }
+        }
+
+        // Make sure that the whole file is valid before merging any of it
+        if let Err(e) = toml::from_str::<ViewerOptions>(&str_buf) {
+            // Prefer the diagnostics, as they point at every problem instead of the first one
+            let e = if diagnostics.is_empty() {
+                ViewerOptionConfigError::from(e)
+            } else {
+                ViewerOptionConfigError::Invalid(diagnostics)
+            };
+            return Err(e.into());
+        }
+
+        let mut table: toml::Table =
             toml::from_str(&str_buf).map_err(ViewerOptionConfigError::from)?;
+        if let Some(dir) = path.parent() {
+            absolutize_paths(&mut table, dir);
+        }
+
+        log::debug!("Loaded configuration {}: {table:#?}", path.display());
+        Ok(Some(table))
+    }
+
+    /// Loads the configuration file at `path` and the ones it extends, as one `layer` per file, from the base to `path`.
+    /// `chain` holds the files extending this one, to detect cycles. Returns `None` if there's no file at `path`.
+    fn load_layers(
+        path: &std::path::Path,
+        layer: fn(std::path::PathBuf) -> ConfigLayer,
+        chain: &mut Vec<std::path::PathBuf>,
+    ) -> ViewerResult<Option<Vec<(ConfigLayer, toml::Table)>>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let canonical_path = std::fs::canonicalize(path)?;
+        if let Some(start) = chain.iter().position(|file| *file == canonical_path) {
+            let cycle = chain[start..]
+                .iter()
+                .chain(std::iter::once(&canonical_path))
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>();
+            return Err(ViewerOptionConfigError::ExtendsCycle(cycle.join(" -> ")).into());
+        }
+
+        let Some(mut table) = Self::load_layer(path)? else {
+            return Ok(None);
+        };
+
+        // The file was validated, so `extends` can only be a string
+        let Some(toml::Value::String(base)) = table.remove("extends") else {
+            return Ok(Some(vec![(layer(path.to_path_buf()), table)]));
+        };
+
+        let base_path = path
+            .parent()
+            .map_or_else(|| base.clone().into(), |dir| dir.join(&base));
+        let theme = BundledTheme::find(&base).filter(|_| !base_path.exists());
 
-        ret.button_active_colors
-            .merge_defaults(ViewerColor::active_default());
+        chain.push(canonical_path);
+        let base_layers = match theme {
+            Some(theme) => theme
+                .table()
+                .map(|table| vec![(ConfigLayer::Theme(theme.name.to_string()), table)])
+                .map_err(|e| ViewerOptionConfigError::from(e).into()),
+            None => Self::load_layers(&base_path, layer, chain)
+                .and_then(|layers| layers.ok_or_else(|| ViewerOptionConfigError::NotFound.into())),
+        };
+        chain.pop();
 
-        ret.button_inactive_colors
-            .merge_defaults(ViewerColor::inactive_default());
+        let mut layers = base_layers.map_err(|e| match e {
+            // Errors are reported by the file that extends the failing one
+            ViewerError::ConfigError(
+                ViewerOptionConfigError::Extends { .. } | ViewerOptionConfigError::ExtendsCycle(_),
+            ) => e,
+            e => ViewerOptionConfigError::Extends {
+                file: path.to_path_buf(),
+                base,
+                source: Box::new(e),
+            }
+            .into(),
+        })?;
 
-        ret.path = path;
-        log::debug!("Loaded configuration: {ret:#?}");
-        Ok(ret)
+        layers.push((layer(path.to_path_buf()), table));
+        Ok(Some(layers))
     }
 
     /// Saves the current configuration at the desired path
@@ -125,9 +723,13 @@ impl ViewerOptions {
         let path = if let Some(path) = path.cloned().take() {
             path
         } else {
-            Self::get_cwd()?
+            Self::user_config_path()?
         };
 
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
         let toml_output = toml::to_string_pretty(self).map_err(ViewerOptionConfigError::from)?;
         let _ = std::fs::write(path.clone(), toml_output.into_bytes())?;
         self.path = path;
@@ -135,19 +737,67 @@ impl ViewerOptions {
     }
 
     pub fn run() -> ViewerResult<Option<Self>> {
-        use clap::Parser as _;
+        use clap::FromArgMatches as _;
         // Get configuration from cli
-        let cli_options = Self::parse();
+        let matches = <Self as clap::CommandFactory>::command().get_matches();
+        let cli_options = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
         if cli_options.init_config {
+            let path = match &cli_options.config_path {
+                Some(path) => path.clone(),
+                None => Self::user_config_path()?,
+            };
+
+            if path.exists() {
+                return Err(anyhow::anyhow!(
+                    "{} already exists, provide another path with --config",
+                    path.display()
+                )
+                .into());
+            }
+
             let mut ret = ViewerOptions::default();
-            let _ = ret.save_config(None).unwrap();
-            log::info!("configuration saved in ./b0xx_viewer_config.toml");
+            ret.save_config(Some(&path))?;
+            log::info!("Configuration saved in {}", path.display());
+            return Ok(None);
+        }
+
+        if cli_options.list_themes {
+            BundledTheme::list();
+            return Ok(None);
+        }
+
+        if let Some(theme_path) = &cli_options.import_web_theme {
+            let config_path = match &cli_options.config_path {
+                Some(path) => path.clone(),
+                None => Self::user_config_path()?,
+            };
+            ImportedTheme::import(theme_path, &config_path)?;
+            return Ok(None);
+        }
+
+        if cli_options.check_config || cli_options.migrate_config {
+            let config_path = Self::discovered_config_path(cli_options.config_path.as_deref())?;
+
+            if cli_options.migrate_config {
+                Self::migrate_config(&config_path)?;
+            }
+
+            if cli_options.check_config {
+                Self::check_config(&config_path)?;
+            }
+
             return Ok(None);
         }
 
-        let mut config = Self::load_config(cli_options.config_path.as_ref()).unwrap_or_default();
-        config.merge(cli_options);
+        let (mut config, layers) = Self::resolve(&matches)?;
+
+        if cli_options.print_config {
+            layers
+                .print(&config)
+                .map_err(ViewerOptionConfigError::from)?;
+            return Ok(None);
+        }
 
         if let Some(tty) = config.custom_tty.take() {
             config.custom_tty = serialport::available_ports()?
@@ -171,25 +821,415 @@ impl ViewerOptions {
         Ok(Some(config))
     }
 
-    pub fn merge(&mut self, other: Self) {
-        self.display_labels |= other.display_labels;
-        self.chromeless |= other.chromeless;
-        self.is_r1_b0xx |= other.is_r1_b0xx;
-        self.colored_rims |= other.colored_rims;
-        self.relax_arduino_detection |= other.relax_arduino_detection;
+    /// Resolves the configuration by merging, from the lowest to the highest precedence:
+    /// the defaults, the bundled theme, the system configuration, the user configuration, the `--config` file
+    /// (or the one next to the executable or in the working directory), the environment variables and the command line.
+    /// Configuration files come after the files they extend.
+    ///
+    /// Configuration files that can't be loaded are skipped, and reported in `config_error`.
+    fn resolve(matches: &clap::ArgMatches) -> ViewerResult<(Self, LayeredConfig)> {
+        use clap::FromArgMatches as _;
+        let cli_options = Self::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+        let mut layers =
+            LayeredConfig::new(&Self::default()).map_err(ViewerOptionConfigError::from)?;
+
+        let user_paths = user_config_paths();
+        let local_paths = match &cli_options.config_path {
+            Some(path) => vec![std::path::absolute(path)?],
+            None => local_config_paths(),
+        };
+
+        let system_path = system_config_path();
+        // Not having a configuration file is fine, unless one was explicitly provided
+        let is_required = cli_options.config_path.is_some();
+        let local_path = match is_required {
+            true => local_paths.first(),
+            false => find_config(&local_paths),
+        };
+        let file_layers = [
+            (
+                system_path.as_ref(),
+                ConfigLayer::System as fn(_) -> _,
+                false,
+            ),
+            (find_config(&user_paths), ConfigLayer::User, false),
+            (local_path, ConfigLayer::File, is_required),
+        ];
+
+        // Every candidate is watched, so that creating a configuration file is picked up too
+        let mut layer_files: Vec<_> = system_path
+            .iter()
+            .cloned()
+            .chain(user_paths.iter().cloned())
+            .chain(local_paths.iter().cloned())
+            .collect();
+        let mut errors = vec![];
+        let mut layer_tables = vec![];
+        let mut config_path = None;
+        for (path, layer, is_required) in file_layers {
+            let Some(path) = path else {
+                continue;
+            };
+
+            let result = match Self::load_layers(path, layer, &mut vec![]) {
+                Ok(None) if is_required => Err(ViewerOptionConfigError::NotFound.into()),
+                result => result,
+            };
+
+            match result {
+                Ok(Some(tables)) => {
+                    log::info!("Loaded the configuration {}", path.display());
+                    layer_files.extend(
+                        tables
+                            .iter()
+                            .filter_map(|(layer, _)| layer.path())
+                            .map(std::path::Path::to_path_buf),
+                    );
+                    layer_tables.extend(tables);
+                    config_path = Some(path.clone());
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::error!(
+                        "Could not load the configuration {}, skipping it: {e}",
+                        path.display()
+                    );
+                    errors.push(format!("{}: {e}", path.display()));
+                }
+            }
+        }
+
+        layer_tables.extend(env_layers());
+        layer_tables.push((
+            ConfigLayer::CommandLine,
+            Self::cli_layer(matches, &cli_options)?,
+        ));
+
+        // The theme is the base that every other layer extends, wherever it was selected
+        let theme = layer_tables
+            .iter()
+            .rev()
+            .find_map(|(_, table)| table.get("theme")?.as_str());
+        if let Some(name) = theme {
+            match BundledTheme::find(name) {
+                Some(theme) => layers.merge(
+                    ConfigLayer::Theme(theme.name.to_string()),
+                    theme.table().map_err(ViewerOptionConfigError::from)?,
+                ),
+                None => {
+                    let e = ViewerOptionConfigError::UnknownTheme(name.to_string());
+                    log::error!("{e}");
+                    errors.push(e.to_string());
+                }
+            }
+        }
 
-        if other.background_color != ViewerColor::background_default() {
-            self.background_color = other.background_color;
+        for (layer, table) in layer_tables {
+            layers.merge(layer, table);
         }
 
-        if other.button_inactive_colors != ViewerButtonColors::default_inactive() {
-            self.button_inactive_colors = other.button_inactive_colors;
+        let mut config = layers.options().map_err(ViewerOptionConfigError::from)?;
+        config.path = match config_path {
+            Some(path) => path,
+            None => local_paths.into_iter().next().unwrap_or_default(),
+        };
+        layer_files.sort();
+        layer_files.dedup();
+        config.layer_files = layer_files;
+        config.matches = matches.clone();
+        config.config_path = cli_options.config_path;
+        config.measure_latency = cli_options.measure_latency;
+        config.benchmark = cli_options.benchmark;
+        config.benchmark_report = cli_options.benchmark_report;
+        config.fake_inputs = cli_options.fake_inputs;
+        config.fake_inputs_seed = cli_options.fake_inputs_seed;
+        config.fake_inputs_script = cli_options.fake_inputs_script;
+        config.config_error = (!errors.is_empty()).then(|| errors.join("\n"));
+        config.check_profile();
+
+        Ok((config, layers))
+    }
+
+    /// Returns the settings explicitly set on the command line
+    fn cli_layer(matches: &clap::ArgMatches, cli_options: &Self) -> ViewerResult<toml::Table> {
+        let mut cli_table =
+            toml::Table::try_from(cli_options).map_err(ViewerOptionConfigError::from)?;
+
+        let mut table = toml::Table::new();
+        for id in matches.ids().map(clap::Id::as_str) {
+            if matches.value_source(id) != Some(clap::parser::ValueSource::CommandLine) {
+                continue;
+            }
+
+            // `--no-<flag>` options turn their flag off
+            if let Some(flag) = id.strip_prefix("no_") {
+                table.insert(flag.to_string(), toml::Value::Boolean(false));
+                continue;
+            }
+
+            let key = match id {
+                "profile" => "default_profile",
+                "custom_tty" => "tty",
+                id => id,
+            };
+
+            // Options that aren't settings, such as `--config`, aren't serialized
+            if let Some(value) = cli_table.remove(key) {
+                table.insert(key.to_string(), value);
+            }
         }
 
-        if other.button_active_colors != ViewerButtonColors::default_active() {
-            self.button_active_colors = other.button_active_colors;
+        // Paths given on the command line are relative to the working directory
+        if let Some(toml::Value::String(font)) = table.get_mut("font") {
+            *font = std::path::absolute(&*font)?.to_string_lossy().into_owned();
         }
 
-        self.custom_tty = self.custom_tty.clone().or(other.custom_tty);
+        if let Some(path) = &cli_options.log_file_path {
+            let path = std::path::absolute(path)?.to_string_lossy().into_owned();
+            table.insert("log_file".into(), toml::Value::String(path));
+        }
+
+        Ok(table)
+    }
+
+    /// Prints every problem found in the configuration at `path`.
+    /// Fails with `ViewerError::ConfigCheckFailed` if there are any, so that the viewer exits with a non-zero code.
+    fn check_config(path: &std::path::Path) -> ViewerResult<()> {
+        if !path.exists() {
+            return Err(ViewerOptionConfigError::NotFound.into());
+        }
+
+        let source = std::fs::read_to_string(path)?;
+        let diagnostics = crate::diagnostics::check_config(path, &source);
+        if diagnostics.is_empty() {
+            println!("{}: the configuration is valid", path.display());
+            return Ok(());
+        }
+
+        for diagnostic in &diagnostics {
+            println!("{diagnostic}");
+        }
+
+        Err(ViewerError::ConfigCheckFailed(diagnostics.len()))
+    }
+
+    /// Upgrades the configuration at `path` to the current format.
+    /// The original file is kept next to it, with the version it was upgraded from in its name.
+    fn migrate_config(path: &std::path::Path) -> ViewerResult<()> {
+        if !path.exists() {
+            return Err(ViewerOptionConfigError::NotFound.into());
+        }
+
+        let source = std::fs::read_to_string(path)?;
+        let mut document = source
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e.message().trim()))?;
+
+        let Some(version) = crate::migrations::migrate(&mut document)? else {
+            println!(
+                "{}: the configuration is already up to date",
+                path.display()
+            );
+            return Ok(());
+        };
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{version}.bak"));
+        let backup = std::path::PathBuf::from(backup);
+        std::fs::copy(path, &backup)?;
+        std::fs::write(path, document.to_string())?;
+
+        println!(
+            "{}: upgraded the configuration from version {version} to {CONFIG_VERSION}, the original is saved in {}",
+            path.display(),
+            backup.display()
+        );
+
+        Ok(())
+    }
+
+    /// Unsets the selected profile if the configuration doesn't define it
+    fn check_profile(&mut self) {
+        if let Some(profile) = self.profile.take() {
+            if self.profiles.contains_key(&profile) {
+                self.profile = Some(profile);
+            } else {
+                log::error!("Profile \"{profile}\" not found in the configuration");
+            }
+        }
+    }
+
+    /// Resolves the configuration again with the same command line, picking up the changes made to the configuration files.
+    /// The serial port settings are kept as they are, as the connection is already established.
+    pub fn reload(&self) -> ViewerResult<Self> {
+        let (mut config, _) = Self::resolve(&self.matches)?;
+        config.custom_tty.clone_from(&self.custom_tty);
+        config.relax_arduino_detection = self.relax_arduino_detection;
+        log::trace!("Reloaded configuration: {config:#?}");
+        Ok(config)
+    }
+
+    /// Watches the configuration files of every layer, and calls `on_change` with the reloaded configuration every time one of them changes,
+    /// until it returns false. Does nothing if there are no configuration files to watch.
+    /// The files to watch are updated every time the configuration reloads successfully, as `extends` may have changed.
+    pub fn watch(&self, mut on_change: impl FnMut(ViewerResult<Self>) -> bool + Send + 'static) {
+        if self.layer_files.is_empty() {
+            return;
+        }
+
+        let modified_at = |paths: &[std::path::PathBuf]| {
+            paths
+                .iter()
+                .map(|path| {
+                    std::fs::metadata(path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let options = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("b0xx_viewer_config_watcher".into())
+            .spawn(move || {
+                let mut layer_files = options.layer_files.clone();
+                let mut last_modified_at = modified_at(&layer_files);
+                loop {
+                    std::thread::sleep(CONFIG_WATCH_INTERVAL);
+                    let current_modified_at = modified_at(&layer_files);
+                    if current_modified_at == last_modified_at {
+                        continue;
+                    }
+
+                    last_modified_at = current_modified_at;
+                    log::debug!("Configuration files changed");
+                    let result = options.reload();
+                    // The files that are extended may have changed along with the configuration
+                    if let Ok(config) = &result
+                        && config.layer_files != layer_files
+                    {
+                        log::debug!("Watching {:?}", config.layer_files);
+                        layer_files.clone_from(&config.layer_files);
+                        last_modified_at = modified_at(&layer_files);
+                    }
+
+                    if !on_change(result) {
+                        return;
+                    }
+                }
+            });
+
+        if let Err(e) = spawned {
+            log::error!("Could not watch the configuration files: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Layers = Vec<(ConfigLayer, toml::Table)>;
+
+    /// Writes `files` into a temporary directory named after the test, and loads the layers of the first one
+    fn load(
+        name: &str,
+        files: &[(&str, &str)],
+    ) -> (std::path::PathBuf, ViewerResult<Option<Layers>>) {
+        let dir = std::env::temp_dir().join(format!("b0xx_viewer_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            std::fs::write(dir.join(file), source).unwrap();
+        }
+
+        let dir = std::fs::canonicalize(&dir).unwrap();
+        let layers =
+            ViewerOptions::load_layers(&dir.join(files[0].0), ConfigLayer::File, &mut vec![]);
+        let _ = std::fs::remove_dir_all(&dir);
+        (dir, layers)
+    }
+
+    #[test]
+    fn loads_the_bases_first() {
+        let (dir, layers) = load(
+            "extends",
+            &[
+                ("a.toml", "extends = \"b.toml\"\nscale = 2.0\n"),
+                ("b.toml", "extends = \"gcc\"\nscale = 1.5\n"),
+            ],
+        );
+
+        let layers: Vec<ConfigLayer> = layers
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|(layer, _)| layer)
+            .collect();
+        assert_eq!(
+            layers,
+            [
+                ConfigLayer::Theme("gcc".into()),
+                ConfigLayer::File(dir.join("b.toml")),
+                ConfigLayer::File(dir.join("a.toml")),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_extends_cycles() {
+        let (dir, layers) = load(
+            "extends_cycle",
+            &[
+                ("a.toml", "extends = \"b.toml\"\n"),
+                ("b.toml", "extends = \"a.toml\"\n"),
+            ],
+        );
+
+        match layers {
+            Err(ViewerError::ConfigError(ViewerOptionConfigError::ExtendsCycle(cycle))) => {
+                let [a, b] = ["a.toml", "b.toml"].map(|file| dir.join(file).display().to_string());
+                assert_eq!(cycle, format!("{a} -> {b} -> {a}"));
+            }
+            layers => panic!("expected a cycle, got {layers:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_missing_bases_with_the_file_extending_them() {
+        let (dir, layers) = load(
+            "extends_missing",
+            &[
+                ("a.toml", "extends = \"b.toml\"\n"),
+                ("b.toml", "extends = \"missing.toml\"\n"),
+            ],
+        );
+
+        match layers {
+            Err(ViewerError::ConfigError(ViewerOptionConfigError::Extends {
+                file,
+                base,
+                source,
+            })) => {
+                assert_eq!(file, dir.join("b.toml"));
+                assert_eq!(base, "missing.toml");
+                assert!(matches!(
+                    *source,
+                    ViewerError::ConfigError(ViewerOptionConfigError::NotFound)
+                ));
+            }
+            layers => panic!("expected a missing base, got {layers:?}"),
+        }
+    }
+
+    #[test]
+    fn prefers_local_files_to_bundled_themes() {
+        let (dir, layers) = load(
+            "extends_shadowed_theme",
+            &[("a.toml", "extends = \"gcc\"\n"), ("gcc", "scale = 1.5\n")],
+        );
+
+        let layers = layers.unwrap().unwrap();
+        assert_eq!(layers[0].0, ConfigLayer::File(dir.join("gcc")));
+        assert_eq!(layers[0].1.get("scale"), Some(&toml::Value::Float(1.5)));
     }
 }
@@ -0,0 +1,576 @@
+use crate::animations::MAX_POP_SCALE;
+use crate::b0xx_state::B0xxButton;
+use crate::config::ViewerOptions;
+use crate::layout::{ButtonShape, ViewerButtonStyle};
+
+/// Maximum number of invalid values reported before giving up on a configuration file
+const MAX_DIAGNOSTICS: usize = 100;
+
+pub const MAX_SCALE: f64 = 10.;
+pub const MAX_WINDOW_SIZE: u32 = 16384;
+pub const MAX_LABEL_FONT_SIZE: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The setting is ignored, but the rest of the configuration can be used
+    Warning,
+    /// The configuration file can't be loaded
+    Error,
+}
+
+/// A problem found in a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    pub file: std::path::PathBuf,
+    /// 1-based line and column of the problem, if it could be located
+    pub location: Option<(usize, usize)>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{line}:{column}")?;
+        }
+
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        write!(f, ": {severity}: {}", self.message)
+    }
+}
+
+/// Checks a configuration file, reporting every problem found in it.
+/// Older configuration formats are upgraded before being checked, but the problems are located in `source` as it was written.
+/// An empty list means that the configuration is valid.
+pub fn check_config(file: &std::path::Path, source: &str) -> Vec<ConfigDiagnostic> {
+    let mut checker = ConfigChecker {
+        file,
+        source,
+        diagnostics: vec![],
+    };
+
+    let document = match toml_edit::ImDocument::parse(source) {
+        Ok(document) => document,
+        Err(e) => {
+            checker.report(e.span(), Severity::Error, e.message().trim());
+            return checker.diagnostics;
+        }
+    };
+
+    let mut working_document = document.clone().into_mut();
+    if let Err(e) = crate::migrations::migrate(&mut working_document) {
+        let path = ["version".to_string()];
+        checker.report_at(&document, &path, false, Severity::Error, &e.to_string());
+        return checker.diagnostics;
+    }
+
+    // Deserialize the configuration, taking every invalid value out of it until it loads
+    let mut options = None;
+    let mut ignored_keys = vec![];
+    for _ in 0..MAX_DIAGNOSTICS {
+        ignored_keys.clear();
+        let working_source = working_document.to_string();
+        let mut on_ignored = |path: serde_ignored::Path| ignored_keys.push(ignored_path(&path));
+        let deserializer = serde_ignored::Deserializer::new(
+            toml::Deserializer::new(&working_source),
+            &mut on_ignored,
+        );
+
+        match serde_path_to_error::deserialize::<_, ViewerOptions>(deserializer) {
+            Ok(parsed) => {
+                options = Some(parsed);
+                break;
+            }
+            Err(e) => {
+                let path = error_path(e.path());
+                let message = e.inner().message().trim().to_string();
+                checker.report_at(&document, &path, false, Severity::Error, &message);
+
+                if path.is_empty() || !remove_key(&mut working_document, &path) {
+                    break;
+                }
+            }
+        }
+    }
+
+    for path in &ignored_keys {
+        let message = format!("unknown setting `{}`", path.join("."));
+        checker.report_at(&document, path, true, Severity::Warning, &message);
+    }
+
+    if let Some(options) = options {
+        for (path, message) in validate(file, &options) {
+            let path: Vec<String> = path.split('.').map(String::from).collect();
+            checker.report_at(&document, &path, false, Severity::Warning, &message);
+        }
+    }
+
+    checker.diagnostics
+}
+
+/// Summarizes a list of diagnostics in a single line, for on-screen display
+pub fn summarize(diagnostics: &[ConfigDiagnostic]) -> String {
+    match diagnostics {
+        [] => String::new(),
+        [diagnostic] => diagnostic.to_string(),
+        [diagnostic, rest @ ..] => format!("{diagnostic} (and {} more)", rest.len()),
+    }
+}
+
+/// Checks the values that deserialize fine but are out of their valid range.
+/// Returns the dotted path of each invalid setting along with the reason.
+fn validate(file: &std::path::Path, options: &ViewerOptions) -> Vec<(String, String)> {
+    let mut problems = vec![];
+
+    if options.scale.is_nan() || options.scale <= 0. || options.scale > MAX_SCALE {
+        problems.push((
+            "scale".into(),
+            format!("scale must be greater than 0 and at most {MAX_SCALE}"),
+        ));
+    }
+
+    for (key, value) in [
+        ("window_width", options.window_width),
+        ("window_height", options.window_height),
+    ] {
+        if !(1..=MAX_WINDOW_SIZE).contains(&value) {
+            problems.push((
+                key.into(),
+                format!("{key} must be between 1 and {MAX_WINDOW_SIZE}"),
+            ));
+        }
+    }
+
+    if !(1..=MAX_LABEL_FONT_SIZE).contains(&options.label_font_size) {
+        problems.push((
+            "label_font_size".into(),
+            format!("label_font_size must be between 1 and {MAX_LABEL_FONT_SIZE}"),
+        ));
+    }
+
+    if !crate::config::is_valid_msaa(options.msaa) {
+        problems.push((
+            "msaa".into(),
+            format!(
+                "msaa must be 0 or a power of two up to {}",
+                crate::config::MAX_MSAA
+            ),
+        ));
+    }
+
+    if options.max_fps == Some(0) {
+        problems.push(("max_fps".into(), "max_fps must be at least 1".into()));
+    }
+
+    for (name, animation) in options.animations.iter() {
+        if animation.duration == 0 {
+            problems.push((
+                format!("animations.{name}.duration"),
+                format!("the duration of the {name} animation must be at least 1ms"),
+            ));
+        }
+    }
+
+    if let Some(glow) = &options.animations.glow
+        && (!glow.width.is_finite() || glow.width <= 0.)
+    {
+        problems.push((
+            "animations.glow.width".into(),
+            "the width of the glow must be greater than 0".into(),
+        ));
+    }
+
+    if let Some(pop) = &options.animations.pop
+        && (pop.scale.is_nan() || pop.scale <= 0. || pop.scale > MAX_POP_SCALE)
+    {
+        problems.push((
+            "animations.pop.scale".into(),
+            format!("the scale of the pop must be greater than 0 and at most {MAX_POP_SCALE}"),
+        ));
+    }
+
+    validate_style("layout.style", &options.layout.style, &mut problems);
+    for (button, position) in &options.layout.buttons {
+        let key = format!("layout.buttons.{}.style", button_key(*button));
+        validate_style(&key, &position.style, &mut problems);
+    }
+
+    if let Some(profile) = &options.profile
+        && !options.profiles.contains_key(profile)
+    {
+        problems.push((
+            "default_profile".into(),
+            format!("profile `{profile}` is not defined in `profiles`"),
+        ));
+    }
+
+    if let Some(theme) = &options.theme
+        && crate::themes::BundledTheme::find(theme).is_none()
+    {
+        problems.push((
+            "theme".into(),
+            format!("unknown theme `{theme}`, run with --list-themes to see the bundled themes"),
+        ));
+    }
+
+    // Relative paths are resolved against the configuration file
+    let config_dir = file.parent().unwrap_or(std::path::Path::new(""));
+    if let Some(base) = &options.extends
+        && !config_dir.join(base).is_file()
+        && crate::themes::BundledTheme::find(base).is_none()
+    {
+        problems.push((
+            "extends".into(),
+            format!("`{base}` is neither a configuration file nor a bundled theme"),
+        ));
+    }
+
+    if let Some(font) = &options.font
+        && !config_dir.join(font).is_file()
+    {
+        problems.push((
+            "font".into(),
+            format!("font file `{}` does not exist", font.display()),
+        ));
+    }
+
+    for image in options.skin.paths() {
+        if !config_dir.join(image).is_file() {
+            problems.push((
+                "skin".into(),
+                format!("skin image `{}` does not exist", image.display()),
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Checks the sizes of a button style, `key` being its dotted path
+fn validate_style(key: &str, style: &ViewerButtonStyle, problems: &mut Vec<(String, String)>) {
+    for (name, value) in [("width", style.width), ("height", style.height)] {
+        if value.is_some_and(|value| !value.is_finite() || value <= 0.) {
+            problems.push((
+                format!("{key}.{name}"),
+                format!("the {name} of a button must be greater than 0"),
+            ));
+        }
+    }
+
+    for (name, value) in [
+        ("corner_radius", style.corner_radius),
+        ("rim_thickness", style.rim_thickness),
+        (
+            "inner_shadow.size",
+            style.inner_shadow.map(|shadow| shadow.size),
+        ),
+    ] {
+        if value.is_some_and(|value| !value.is_finite() || value < 0.) {
+            problems.push((format!("{key}.{name}"), format!("{name} can't be negative")));
+        }
+    }
+
+    if let Some(ButtonShape::Polygon(points)) = &style.shape
+        && points.len() < 3
+    {
+        problems.push((
+            format!("{key}.shape"),
+            "a polygon must have at least 3 points".into(),
+        ));
+    }
+}
+
+/// Name of a button in the configuration, eg. `mod_x`
+fn button_key(button: B0xxButton) -> String {
+    toml::Value::try_from(button)
+        .ok()
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+struct ConfigChecker<'a> {
+    file: &'a std::path::Path,
+    source: &'a str,
+    diagnostics: Vec<ConfigDiagnostic>,
+}
+
+impl ConfigChecker<'_> {
+    fn report(&mut self, span: Option<std::ops::Range<usize>>, severity: Severity, message: &str) {
+        let location = span.map(|span| line_column(self.source, span.start));
+        self.diagnostics.push(ConfigDiagnostic {
+            file: self.file.to_path_buf(),
+            location,
+            severity,
+            message: message.to_string(),
+        });
+    }
+
+    /// Reports a problem on the setting at `path`, pointing at its key or its value
+    fn report_at(
+        &mut self,
+        document: &toml_edit::ImDocument<&str>,
+        path: &[String],
+        on_key: bool,
+        severity: Severity,
+        message: &str,
+    ) {
+        let span = find_span(document, path, on_key);
+        self.report(span, severity, message);
+    }
+}
+
+/// Locates the setting at `path` in the document. Falls back on the closest parent that can be located.
+fn find_span(
+    document: &toml_edit::ImDocument<&str>,
+    path: &[String],
+    on_key: bool,
+) -> Option<std::ops::Range<usize>> {
+    let mut table: &dyn toml_edit::TableLike = document.as_table();
+    let mut span = None;
+    for (i, key) in path.iter().enumerate() {
+        let Some((key, item)) = table.get_key_value(key) else {
+            break;
+        };
+
+        let is_last = i == path.len() - 1;
+        let item_span = if is_last && on_key {
+            key.span().or_else(|| item.span())
+        } else {
+            item.span().or_else(|| key.span())
+        };
+        span = item_span.or(span);
+
+        match item.as_table_like() {
+            Some(child) if !is_last => table = child,
+            _ => break,
+        }
+    }
+
+    span
+}
+
+/// Removes the setting at `path` from the document. Returns false if it could not be found.
+fn remove_key(document: &mut toml_edit::DocumentMut, path: &[String]) -> bool {
+    let Some((last, parents)) = path.split_last() else {
+        return false;
+    };
+
+    let mut table: &mut dyn toml_edit::TableLike = document.as_table_mut();
+    for key in parents {
+        match table.get_mut(key).and_then(|item| item.as_table_like_mut()) {
+            Some(child) => table = child,
+            None => return false,
+        }
+    }
+
+    table.remove(last).is_some()
+}
+
+fn error_path(path: &serde_path_to_error::Path) -> Vec<String> {
+    path.iter()
+        .map_while(|segment| match segment {
+            serde_path_to_error::Segment::Map { key } => Some(key.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn ignored_path(path: &serde_ignored::Path) -> Vec<String> {
+    match path {
+        serde_ignored::Path::Root => vec![],
+        serde_ignored::Path::Map { parent, key } => {
+            let mut keys = ignored_path(parent);
+            keys.push(key.clone());
+            keys
+        }
+        serde_ignored::Path::Seq { parent, .. }
+        | serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<String> {
+        check_config(std::path::Path::new("b0xx_viewer_config.toml"), source)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_valid_configurations() {
+        assert!(check("version = 2\nbackground_color = \"#131313\"\nmsaa = 4\n").is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_colors_at_their_value() {
+        assert_eq!(
+            check("version = 2\nbackground_color = \"#12345G\"\n"),
+            [
+                "b0xx_viewer_config.toml:2:20: error: Invalid color `#12345G`: `G` is not a hexadecimal digit"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_nested_settings_at_their_key() {
+        assert_eq!(
+            check("[animations.pop]\nduration = 100\n  bounce = true\n"),
+            ["b0xx_viewer_config.toml:3:3: warning: unknown setting `animations.pop.bounce`"]
+        );
+    }
+
+    #[test]
+    fn reports_out_of_range_values() {
+        assert_eq!(
+            check("msaa = 3\n"),
+            ["b0xx_viewer_config.toml:1:8: warning: msaa must be 0 or a power of two up to 16"]
+        );
+    }
+
+    #[test]
+    fn reports_every_problem_of_a_file() {
+        assert_eq!(
+            check(
+                "background_color = \"red\"\nwindow_width = \"wide\"\nbutton_active_colors = { a = \"#GGG\", b = \"blue\" }\nmsaa = 3\nfoo = 1\n"
+            ),
+            [
+                "b0xx_viewer_config.toml:2:16: error: invalid type: string \"wide\", expected u32",
+                "b0xx_viewer_config.toml:3:30: error: Invalid color `#GGG`: `G` is not a hexadecimal digit",
+                "b0xx_viewer_config.toml:5:1: warning: unknown setting `foo`",
+                "b0xx_viewer_config.toml:4:8: warning: msaa must be 0 or a power of two up to 16",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(
+            check("scale = 1.0\nmsaa = \n"),
+            ["b0xx_viewer_config.toml:2:8: error: invalid string\nexpected `\"`, `'`"]
+        );
+    }
+
+    #[test]
+    fn locates_problems_in_the_file_before_its_migration() {
+        assert_eq!(
+            check("is_r2_b0xx = true\nmsaa = 3\n"),
+            ["b0xx_viewer_config.toml:2:8: warning: msaa must be 0 or a power of two up to 16"]
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_versions() {
+        let diagnostics = check("version = 3\nmsaa = 3\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("b0xx_viewer_config.toml:1:11: error: "));
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let source = "a = \"é\"\nb = \"ñé\" # ü\n";
+        assert_eq!(line_column(source, 0), (1, 1));
+        assert_eq!(line_column(source, source.find('#').unwrap()), (2, 10));
+        assert_eq!(line_column(source, source.len()), (3, 1));
+        assert_eq!(
+            check("# café\nbutton_active_colors = { \"ü\" = \"red\", a = \"redd\" }\n"),
+            [
+                "b0xx_viewer_config.toml:2:43: error: Invalid color `redd`: unknown color, expected a hex color such as \"#00EBFF\", rgb(), rgba(), hsl(), hsla() or a CSS color name",
+                "b0xx_viewer_config.toml:2:26: warning: unknown setting `button_active_colors.ü`",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_keys_and_values() {
+        let source = "msaa = 4\n\n[animations.pop]\nscale = 1.1\n";
+        let document = toml_edit::ImDocument::parse(source).unwrap();
+        let span = |keys: &[&str], on_key| {
+            find_span(&document, &path(keys), on_key).map(|span| &source[span])
+        };
+
+        assert_eq!(span(&["msaa"], true), Some("msaa"));
+        assert_eq!(span(&["msaa"], false), Some("4"));
+        assert_eq!(span(&["animations", "pop", "scale"], true), Some("scale"));
+        assert_eq!(span(&["animations", "pop", "scale"], false), Some("1.1"));
+        // Falls back on the closest parent
+        assert_eq!(
+            span(&["animations", "pop", "duration"], false),
+            Some("[animations.pop]\nscale = 1.1")
+        );
+        assert_eq!(span(&["scale"], false), None);
+    }
+
+    #[test]
+    fn removes_nested_keys() {
+        let mut document = "msaa = 4\n\n[animations.pop]\nscale = 1.1\nduration = 100\n"
+            .parse::<toml_edit::DocumentMut>()
+            .unwrap();
+
+        assert!(remove_key(
+            &mut document,
+            &path(&["animations", "pop", "scale"])
+        ));
+        assert!(!remove_key(
+            &mut document,
+            &path(&["animations", "glow", "width"])
+        ));
+        assert!(!remove_key(&mut document, &path(&["msaa", "samples"])));
+        assert!(!remove_key(&mut document, &[]));
+        assert_eq!(
+            document.to_string(),
+            "msaa = 4\n\n[animations.pop]\nduration = 100\n"
+        );
+    }
+
+    #[test]
+    fn validates_ranges() {
+        let mut options = ViewerOptions::default();
+        options.scale = 0.;
+        options.window_width = MAX_WINDOW_SIZE + 1;
+        options.label_font_size = 0;
+        options.msaa = 6;
+        options.max_fps = Some(0);
+
+        let keys: Vec<String> = validate(std::path::Path::new(""), &options)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "scale",
+                "window_width",
+                "label_font_size",
+                "msaa",
+                "max_fps"
+            ]
+        );
+        assert!(validate(std::path::Path::new(""), &ViewerOptions::default()).is_empty());
+    }
+}
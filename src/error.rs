@@ -8,10 +8,16 @@ pub enum ViewerError {
     IoError(#[from] std::io::Error),
     #[error(transparent)]
     SerialPortError(#[from] serialport::Error),
-    #[error("Internal serial thread error: {0}")]
-    SerialThreadError(#[from] crossbeam_channel::RecvError),
     #[error("Configuration error: {0}")]
     ConfigError(#[from] ViewerOptionConfigError),
+    #[error("{}:{line}: {reason}", .path.display())]
+    InvalidFakeInputsScript {
+        path: std::path::PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("{0} problem(s) found in the configuration")]
+    ConfigCheckFailed(usize),
     #[error("The state report transmitted over serial was malformed")]
     MalformedSerialReport,
     #[error(transparent)]
@@ -22,14 +28,59 @@ pub enum ViewerError {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ViewerOptionConfigError {
-    #[error("Could not parse hex number to its numerical components")]
-    HexNumberParseError(#[from] std::num::ParseIntError),
+    #[error("Invalid color `{color}`: {reason}")]
+    InvalidColor { color: String, reason: String },
     #[error("The supplied config path could not be found on the filesystem.")]
     NotFound,
     #[error("TOML Deserialization Error: {0}")]
     DeserializationError(#[from] toml::de::Error),
     #[error("TOML Serialization Error: {0}")]
     SerializationError(#[from] toml::ser::Error),
+    #[error("Invalid configuration: {}", crate::diagnostics::summarize(.0))]
+    Invalid(Vec<crate::diagnostics::ConfigDiagnostic>),
+    #[error(
+        "The configuration format version {} is newer than the supported version {}, please update the viewer",
+        .0,
+        crate::migrations::CONFIG_VERSION
+    )]
+    UnsupportedVersion(i64),
+    #[error("Unknown theme `{0}`, run with --list-themes to see the bundled themes")]
+    UnknownTheme(String),
+    #[error("{}: could not extend `{base}`: {source}", .file.display())]
+    Extends {
+        file: std::path::PathBuf,
+        base: String,
+        source: Box<ViewerError>,
+    },
+    #[error("The configuration files extend each other: {0}")]
+    ExtendsCycle(String),
+}
+
+const SERIAL_PERMISSION_DENIED: &str =
+    "Not allowed to open the serial port. On Linux, your user needs to be in the `dialout` group";
+
+impl ViewerError {
+    /// Describes the error for the overlay of the viewer, with hints on how to fix it when possible
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::IoError(e) => match e.kind() {
+                std::io::ErrorKind::TimedOut => "The B0XX stopped responding".into(),
+                std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::NotConnected => {
+                    "The B0XX was disconnected".into()
+                }
+                std::io::ErrorKind::PermissionDenied => SERIAL_PERMISSION_DENIED.into(),
+                _ => format!("Could not communicate with the B0XX: {e}"),
+            },
+            Self::SerialPortError(e) => match e.kind() {
+                serialport::ErrorKind::NoDevice => "The B0XX was disconnected".into(),
+                serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => {
+                    SERIAL_PERMISSION_DENIED.into()
+                }
+                _ => format!("Could not open the serial port: {}", e.description),
+            },
+            e => e.to_string(),
+        }
+    }
 }
 
 pub type ViewerResult<T> = Result<T, ViewerError>;
@@ -0,0 +1,400 @@
+use crate::b0xx_state::{B0xxButton, B0xxState};
+use crate::error::{ViewerError, ViewerResult};
+use rand::{Rng as _, SeedableRng as _, rngs::SmallRng};
+
+/// Interval between two fake reports, close to the one of a B0XX
+pub const REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_micros(8700);
+/// Duration of a frame of Melee, which runs at 60 FPS
+const FRAME: std::time::Duration = std::time::Duration::from_micros(16_667);
+
+/// Generator of the fake inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FakeInputsMode {
+    /// A new random state on every report
+    Random,
+    /// States played from the file given with --fake-inputs-script, in a loop
+    Script,
+    /// Plausible Melee techniques, with SOCD overlaps and modifier combos
+    Realistic,
+}
+
+/// State of the buttons held for a given duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FakeStep {
+    pub state: B0xxState,
+    pub duration: std::time::Duration,
+}
+
+/// Source of inputs replacing the B0XX, for demos and automated tests
+#[derive(Debug, Clone)]
+pub struct FakeInputs {
+    mode: FakeInputsMode,
+    /// Seed of the random and realistic generators, so that their inputs can be reproduced
+    seed: u64,
+    script: Vec<FakeStep>,
+    /// Sent at the pace of a B0XX, or as fast as possible for benchmarks
+    throttled: bool,
+}
+
+impl FakeInputs {
+    /// Prepares the fake inputs, loading the script if needed. Without a seed, a new one is picked and logged.
+    pub fn new(
+        mode: FakeInputsMode,
+        seed: Option<u64>,
+        script_path: Option<&std::path::Path>,
+    ) -> ViewerResult<Self> {
+        let script = match mode {
+            FakeInputsMode::Script => {
+                let path = script_path.ok_or_else(|| {
+                    anyhow::anyhow!("--fake-inputs script requires --fake-inputs-script")
+                })?;
+                load_script(path)?
+            }
+            _ => vec![],
+        };
+
+        let seed = seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64
+        });
+        if mode != FakeInputsMode::Script {
+            log::info!("Fake inputs seed: {seed}, replay them with --fake-inputs-seed {seed}");
+        }
+
+        Ok(Self {
+            mode,
+            seed,
+            script,
+            throttled: true,
+        })
+    }
+
+    /// Sets whether the steps are played at the pace of a B0XX, or each reported once as fast as possible
+    pub fn throttled(self, throttled: bool) -> Self {
+        Self { throttled, ..self }
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.throttled
+    }
+
+    /// Name of the fake device, shown in place of the port name
+    pub fn name(&self) -> String {
+        match self.mode {
+            FakeInputsMode::Random => format!("fake inputs (random, seed {})", self.seed),
+            FakeInputsMode::Script => "fake inputs (script)".into(),
+            FakeInputsMode::Realistic => format!("fake inputs (realistic, seed {})", self.seed),
+        }
+    }
+
+    /// Returns the endless sequence of states to play, the same one every time
+    pub fn steps(&self) -> Box<dyn Iterator<Item = FakeStep> + Send> {
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        match self.mode {
+            FakeInputsMode::Random => Box::new(std::iter::repeat_with(move || FakeStep {
+                state: B0xxState::random(&mut rng),
+                duration: REPORT_INTERVAL,
+            })),
+            FakeInputsMode::Script => Box::new(self.script.clone().into_iter().cycle()),
+            FakeInputsMode::Realistic => Box::new(MeleeInputs::new(rng)),
+        }
+    }
+}
+
+/// Parses a script, made of one step per line: a duration followed by the buttons held during it.
+///
+/// ```text
+/// # Comments start with a `#`
+/// 100ms a
+/// 3f down b      # Durations are in milliseconds, or in frames with the `f` suffix
+/// 50             # No buttons means none is pressed
+/// 4f mod_x+right # Buttons are separated by spaces or `+`
+/// ```
+fn load_script(path: &std::path::Path) -> ViewerResult<Vec<FakeStep>> {
+    let invalid = |line: usize, reason: String| ViewerError::InvalidFakeInputsScript {
+        path: path.to_path_buf(),
+        line,
+        reason,
+    };
+
+    let mut steps = vec![];
+    for (index, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split(|c: char| c.is_whitespace() || c == '+');
+        let Some(duration) = words.find(|word| !word.is_empty()) else {
+            continue;
+        };
+
+        let duration = parse_duration(duration).ok_or_else(|| {
+            invalid(
+                index + 1,
+                format!("invalid duration `{duration}`, expected eg. `100ms` or `3f`"),
+            )
+        })?;
+        let state = words
+            .filter(|word| !word.is_empty())
+            .map(|name| {
+                parse_button(name)
+                    .ok_or_else(|| invalid(index + 1, format!("unknown button `{name}`")))
+            })
+            .collect::<ViewerResult<B0xxState>>()?;
+
+        steps.push(FakeStep { state, duration });
+    }
+
+    if steps.is_empty() {
+        return Err(anyhow::anyhow!("{}: the script has no steps", path.display()).into());
+    }
+
+    Ok(steps)
+}
+
+/// Parses `100`, `100ms` or `6f`, in frames of Melee
+fn parse_duration(duration: &str) -> Option<std::time::Duration> {
+    if let Some(frames) = duration.strip_suffix('f') {
+        return Some(FRAME * frames.parse::<u32>().ok()?);
+    }
+
+    let ms = duration.strip_suffix("ms").unwrap_or(duration);
+    Some(std::time::Duration::from_millis(ms.parse().ok()?))
+}
+
+/// Parses the name of a button, as used in the `[labels]` and `[layout]` tables
+fn parse_button(name: &str) -> Option<B0xxButton> {
+    use serde::Deserialize as _;
+    use serde::de::IntoDeserializer as _;
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        name.into_deserializer();
+    B0xxButton::deserialize(deserializer).ok()
+}
+
+/// Generates plausible Melee inputs: movement, techniques and modifier combos, separated by short pauses.
+/// Like a player would, opposite directions overlap for a few frames (SOCD), the last one pressed winning in game.
+struct MeleeInputs {
+    rng: SmallRng,
+    pending: std::collections::VecDeque<FakeStep>,
+}
+
+impl MeleeInputs {
+    fn new(rng: SmallRng) -> Self {
+        Self {
+            rng,
+            pending: Default::default(),
+        }
+    }
+
+    /// Holds `buttons` for a number of frames picked in `frames`
+    fn hold(&mut self, frames: std::ops::RangeInclusive<u32>, buttons: &[B0xxButton]) {
+        let frames = self.rng.gen_range(frames);
+        self.pending.push_back(FakeStep {
+            state: buttons.iter().copied().collect(),
+            duration: FRAME * frames,
+        });
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.rng.gen_range(0..items.len())]
+    }
+
+    fn queue_technique(&mut self) {
+        use B0xxButton::*;
+
+        let side = self.pick(&[Left, Right]);
+        let other_side = if side == Left { Right } else { Left };
+        let jump = self.pick(&[X, Y]);
+        let c_stick = self.pick(&[CLeft, CRight, CUp, CDown]);
+
+        match self.rng.gen_range(0..10) {
+            // Run, or walk with ModX
+            0 => {
+                if self.rng.gen_bool(0.3) {
+                    self.hold(15..=60, &[ModX, side]);
+                } else {
+                    self.hold(15..=60, &[side]);
+                }
+            }
+            // Dash dance, pressing the next direction before releasing the previous one
+            1 => {
+                let (mut side, mut other_side) = (side, other_side);
+                for _ in 0..self.rng.gen_range(3..=6) {
+                    self.hold(4..=8, &[side]);
+                    self.hold(1..=2, &[side, other_side]);
+                    std::mem::swap(&mut side, &mut other_side);
+                }
+                self.hold(3..=6, &[side]);
+            }
+            // Wavedash, shorter with ModX
+            2 => {
+                self.hold(3..=3, &[jump]);
+                if self.rng.gen_bool(0.4) {
+                    self.hold(1..=2, &[L, Down, side, ModX]);
+                } else {
+                    self.hold(1..=2, &[L, Down, side]);
+                }
+                self.hold(8..=12, &[Down]);
+            }
+            // Multishine
+            3 => {
+                for _ in 0..self.rng.gen_range(2..=4) {
+                    self.hold(2..=2, &[Down, B]);
+                    self.hold(3..=3, &[jump]);
+                }
+                self.hold(2..=2, &[Down, B]);
+            }
+            // Short hop aerial with the C-stick, fast fall and L-cancel
+            4 => {
+                self.hold(2..=2, &[jump]);
+                self.hold(4..=10, &[side]);
+                self.hold(2..=3, &[side, c_stick]);
+                self.hold(6..=12, &[]);
+                self.hold(2..=3, &[Down]);
+                self.hold(1..=1, &[L]);
+            }
+            // Up B, angled with ModX or ModY
+            5 => {
+                self.hold(2..=2, &[Up, B]);
+                self.hold(30..=40, &[]);
+                let modifier = self.pick(&[ModX, ModY]);
+                self.hold(3..=6, &[modifier, Up, side]);
+            }
+            // Side B, shortened by pressing B again
+            6 => {
+                self.hold(2..=3, &[side, B]);
+                self.hold(5..=15, &[]);
+                self.hold(1..=2, &[B]);
+            }
+            // Shield and light shield, then a shield drop with ModY
+            7 => {
+                let shield = self.pick(&[R, ModLs, ModMs]);
+                self.hold(10..=40, &[shield]);
+                if shield == R {
+                    self.hold(2..=3, &[R, ModY, Down]);
+                }
+            }
+            // Crouch then jump out of it, pressing up before releasing down
+            8 => {
+                self.hold(10..=30, &[Down]);
+                self.hold(1..=2, &[Down, Up]);
+                self.hold(3..=5, &[Up]);
+            }
+            // Tilt with ModX, grab or smash with the C-stick
+            _ => match self.rng.gen_range(0..3) {
+                0 => self.hold(4..=8, &[ModX, side, A]),
+                1 => {
+                    self.hold(2..=2, &[Z]);
+                    self.hold(10..=20, &[]);
+                    self.hold(2..=4, &[side]);
+                }
+                _ => self.hold(3..=5, &[c_stick]),
+            },
+        }
+
+        self.hold(4..=20, &[]);
+    }
+}
+
+impl Iterator for MeleeInputs {
+    type Item = FakeStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            self.queue_technique();
+        }
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `script` from a temporary file named after the test
+    fn load(name: &str, script: &str) -> ViewerResult<Vec<FakeStep>> {
+        let path =
+            std::env::temp_dir().join(format!("b0xx_viewer_{name}_{}.txt", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        let steps = load_script(&path);
+        let _ = std::fs::remove_file(&path);
+        steps
+    }
+
+    fn step(duration: std::time::Duration, buttons: &[B0xxButton]) -> FakeStep {
+        FakeStep {
+            state: buttons.iter().copied().collect(),
+            duration,
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        let ms = std::time::Duration::from_millis;
+        assert_eq!(parse_duration("100"), Some(ms(100)));
+        assert_eq!(parse_duration("100ms"), Some(ms(100)));
+        assert_eq!(parse_duration("0ms"), Some(ms(0)));
+        assert_eq!(parse_duration("1f"), Some(FRAME));
+        assert_eq!(parse_duration("6f"), Some(FRAME * 6));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for duration in [
+            "",
+            "ms",
+            "f",
+            "-1",
+            "1.5",
+            "1s",
+            "3 f",
+            "10fms",
+            "99999999999f",
+        ] {
+            assert_eq!(parse_duration(duration), None, "{duration}");
+        }
+    }
+
+    #[test]
+    fn parses_scripts() {
+        let steps = load(
+            "parses_scripts",
+            "# Comments start with a `#`\n100ms a\n3f down b      # frames\n\n50\n4f mod_x+right\n",
+        )
+        .unwrap();
+
+        let ms = std::time::Duration::from_millis;
+        assert_eq!(
+            steps,
+            [
+                step(ms(100), &[B0xxButton::A]),
+                step(FRAME * 3, &[B0xxButton::Down, B0xxButton::B]),
+                step(ms(50), &[]),
+                step(FRAME * 4, &[B0xxButton::ModX, B0xxButton::Right]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_scripts() {
+        let error = |name: &str, script: &str| load(name, script).unwrap_err().to_string();
+
+        assert!(
+            error("invalid_duration", "100ms a\nsoon b\n")
+                .ends_with(":2: invalid duration `soon`, expected eg. `100ms` or `3f`")
+        );
+        assert!(
+            error("unknown_button", "\n\n100ms a+turbo\n").ends_with(":3: unknown button `turbo`")
+        );
+        assert!(error("no_steps", "# nothing\n\n").ends_with(": the script has no steps"));
+    }
+
+    #[test]
+    fn seeded_inputs_are_reproducible() {
+        for mode in [FakeInputsMode::Random, FakeInputsMode::Realistic] {
+            let inputs = FakeInputs::new(mode, Some(42), None).unwrap();
+            let first: Vec<_> = inputs.steps().take(100).collect();
+            let second: Vec<_> = inputs.steps().take(100).collect();
+            assert_eq!(first, second);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::b0xx_state::B0xxButton;
+
+/// Built-in sets of button labels
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelSet {
+    /// Short names of the buttons, as printed on the B0XX
+    #[default]
+    B0xx,
+    /// Names of the actions the buttons perform in Melee
+    Melee,
+    /// Arrows for every directional button
+    Icons,
+}
+
+impl LabelSet {
+    pub const ALL: [LabelSet; 3] = [LabelSet::B0xx, LabelSet::Melee, LabelSet::Icons];
+
+    /// Returns the set following this one, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|set| *set == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self, button: B0xxButton) -> &'static str {
+        match self {
+            LabelSet::B0xx => b0xx_label(button),
+            LabelSet::Melee => melee_label(button),
+            LabelSet::Icons => icon_label(button),
+        }
+    }
+}
+
+fn b0xx_label(button: B0xxButton) -> &'static str {
+    match button {
+        B0xxButton::Start => "SRT",
+        B0xxButton::Y => "Y",
+        B0xxButton::X => "X",
+        B0xxButton::B => "B",
+        B0xxButton::A => "A",
+        B0xxButton::L => "L",
+        B0xxButton::R => "R",
+        B0xxButton::Z => "Z",
+        B0xxButton::Up => "↑",
+        B0xxButton::Down => "↓",
+        B0xxButton::Right => "→",
+        B0xxButton::Left => "←",
+        B0xxButton::ModX => "MX",
+        B0xxButton::ModY => "MY",
+        B0xxButton::CLeft => "CL",
+        B0xxButton::CRight => "CR",
+        B0xxButton::CUp => "CU",
+        B0xxButton::CDown => "CD",
+        B0xxButton::ModLs => "LS",
+        B0xxButton::ModMs => "MS",
+    }
+}
+
+fn melee_label(button: B0xxButton) -> &'static str {
+    match button {
+        B0xxButton::Start => "Pause",
+        B0xxButton::Y | B0xxButton::X => "Jump",
+        B0xxButton::B => "Special",
+        B0xxButton::A => "Attack",
+        B0xxButton::L | B0xxButton::R => "Shield",
+        B0xxButton::Z => "Grab",
+        B0xxButton::Up => "↑",
+        B0xxButton::Down => "↓",
+        B0xxButton::Right => "→",
+        B0xxButton::Left => "←",
+        B0xxButton::ModX => "Mod X",
+        B0xxButton::ModY => "Mod Y",
+        B0xxButton::CLeft => "C←",
+        B0xxButton::CRight => "C→",
+        B0xxButton::CUp => "C↑",
+        B0xxButton::CDown => "C↓",
+        B0xxButton::ModLs => "Light",
+        B0xxButton::ModMs => "Mid",
+    }
+}
+
+fn icon_label(button: B0xxButton) -> &'static str {
+    match button {
+        B0xxButton::Start => "•",
+        B0xxButton::Up | B0xxButton::CUp => "↑",
+        B0xxButton::Down | B0xxButton::CDown => "↓",
+        B0xxButton::Right | B0xxButton::CRight => "→",
+        B0xxButton::Left | B0xxButton::CLeft => "←",
+        _ => b0xx_label(button),
+    }
+}
+
+/// Custom labels overriding the ones of the current label set
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerButtonLabels {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub b: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub a: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub down: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_y: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_left: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_right: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_up: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub c_down: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_ls: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_ms: Option<String>,
+}
+
+impl ViewerButtonLabels {
+    pub fn get(&self, button: B0xxButton) -> Option<&str> {
+        match button {
+            B0xxButton::Start => self.start.as_deref(),
+            B0xxButton::Y => self.y.as_deref(),
+            B0xxButton::X => self.x.as_deref(),
+            B0xxButton::B => self.b.as_deref(),
+            B0xxButton::A => self.a.as_deref(),
+            B0xxButton::L => self.l.as_deref(),
+            B0xxButton::R => self.r.as_deref(),
+            B0xxButton::Z => self.z.as_deref(),
+            B0xxButton::Up => self.up.as_deref(),
+            B0xxButton::Down => self.down.as_deref(),
+            B0xxButton::Right => self.right.as_deref(),
+            B0xxButton::Left => self.left.as_deref(),
+            B0xxButton::ModX => self.mod_x.as_deref(),
+            B0xxButton::ModY => self.mod_y.as_deref(),
+            B0xxButton::CLeft => self.c_left.as_deref(),
+            B0xxButton::CRight => self.c_right.as_deref(),
+            B0xxButton::CUp => self.c_up.as_deref(),
+            B0xxButton::CDown => self.c_down.as_deref(),
+            B0xxButton::ModLs => self.mod_ls.as_deref(),
+            B0xxButton::ModMs => self.mod_ms.as_deref(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        B0xxButton::ALL
+            .iter()
+            .all(|button| self.get(*button).is_none())
+    }
+
+    /// Returns the custom label of a button, or its label in the given set
+    pub fn label(&self, button: B0xxButton, set: LabelSet) -> &str {
+        self.get(button).unwrap_or_else(|| set.label(button))
+    }
+}
@@ -0,0 +1,287 @@
+/// Number of samples the live histograms are computed over
+const LIVE_SAMPLES: usize = 1024;
+/// Percentiles shown in the histograms and printed by `--measure-latency`
+pub const PERCENTILES: [f64; 4] = [50., 90., 95., 99.];
+
+/// Durations measured over time, keeping the latest ones only if a capacity is set
+#[derive(Debug, Clone, Default)]
+pub struct Samples {
+    values: std::collections::VecDeque<std::time::Duration>,
+    capacity: Option<usize>,
+}
+
+impl Samples {
+    fn new(capacity: Option<usize>) -> Self {
+        Self {
+            values: Default::default(),
+            capacity,
+        }
+    }
+
+    /// Keeps every sample
+    pub fn unbounded() -> Self {
+        Self::new(None)
+    }
+
+    pub fn push(&mut self, value: std::time::Duration) {
+        if self
+            .capacity
+            .is_some_and(|capacity| self.values.len() >= capacity)
+        {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn count(&self) -> usize {
+        self.values.len()
+    }
+
+    fn sorted(&self) -> Vec<std::time::Duration> {
+        let mut values: Vec<_> = self.values.iter().copied().collect();
+        values.sort_unstable();
+        values
+    }
+
+    /// Returns the given percentiles, in `0.0..=100.0`, using the nearest-rank method
+    pub fn percentiles<const N: usize>(
+        &self,
+        percentiles: [f64; N],
+    ) -> Option<[std::time::Duration; N]> {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return None;
+        }
+
+        Some(percentiles.map(|percentile| {
+            let rank = (percentile / 100. * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        }))
+    }
+
+    pub fn max(&self) -> Option<std::time::Duration> {
+        self.values.iter().max().copied()
+    }
+
+    pub fn mean(&self) -> Option<std::time::Duration> {
+        let count = u32::try_from(self.values.len())
+            .ok()
+            .filter(|count| *count > 0)?;
+        Some(self.values.iter().sum::<std::time::Duration>() / count)
+    }
+
+    /// Standard deviation of the samples
+    pub fn std_dev(&self) -> Option<std::time::Duration> {
+        if self.values.is_empty() {
+            return None;
+        }
+
+        let count = self.values.len() as f64;
+        let mean = self.values.iter().map(|d| d.as_secs_f64()).sum::<f64>() / count;
+        let variance = self
+            .values
+            .iter()
+            .map(|d| (d.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        Some(std::time::Duration::from_secs_f64(variance.sqrt()))
+    }
+
+    /// Counts the samples in `buckets` buckets of the same width, from 0 to `max`.
+    /// Samples above `max` are counted in the last bucket.
+    pub fn histogram(&self, buckets: usize, max: std::time::Duration) -> Vec<usize> {
+        let mut histogram = vec![0; buckets];
+        if buckets == 0 || max.is_zero() {
+            return histogram;
+        }
+
+        for value in &self.values {
+            let bucket = (value.as_secs_f64() / max.as_secs_f64() * buckets as f64) as usize;
+            histogram[bucket.min(buckets - 1)] += 1;
+        }
+        histogram
+    }
+
+    /// Describes the percentiles of the samples, eg. `p50 1.20ms · p90 ...`
+    pub fn summary(&self) -> String {
+        let Some(values) = self.percentiles(PERCENTILES) else {
+            return "no samples".into();
+        };
+
+        PERCENTILES
+            .iter()
+            .zip(values)
+            .map(|(percentile, value)| format!("p{percentile} {}", format_duration(value)))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+pub fn format_duration(duration: std::time::Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.)
+}
+
+/// Measures the latency added by the viewer, and the regularity of the reports of the B0XX
+#[derive(Debug, Clone, Default)]
+pub struct LatencyMonitor {
+    /// From a report being read on the serial port to the frame showing it being presented
+    pub latency: Samples,
+    /// Between two consecutive reports
+    pub report_interval: Samples,
+    last_report_at: Option<std::time::Instant>,
+    /// Read time of the oldest report that changed the state and wasn't presented yet
+    pending_report_at: Option<std::time::Instant>,
+}
+
+impl LatencyMonitor {
+    /// Keeps the latest samples only, for the live histograms
+    pub fn live() -> Self {
+        Self::with_capacity(Some(LIVE_SAMPLES))
+    }
+
+    /// Keeps every sample, for `--measure-latency`
+    pub fn unbounded() -> Self {
+        Self::with_capacity(None)
+    }
+
+    fn with_capacity(capacity: Option<usize>) -> Self {
+        Self {
+            latency: Samples::new(capacity),
+            report_interval: Samples::new(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Records a report read at `read_at`
+    pub fn report_read(&mut self, read_at: std::time::Instant) {
+        if let Some(last_report_at) = self.last_report_at.replace(read_at) {
+            self.report_interval
+                .push(read_at.saturating_duration_since(last_report_at));
+        }
+    }
+
+    /// Records a report read at `read_at` changing the state. Only those reports are shown on screen.
+    pub fn state_changed(&mut self, read_at: std::time::Instant) {
+        self.pending_report_at.get_or_insert(read_at);
+    }
+
+    /// Forgets the last report when the connection is lost, so that the downtime isn't counted as an interval
+    pub fn disconnected(&mut self) {
+        self.last_report_at = None;
+        self.pending_report_at = None;
+    }
+
+    /// Records the presentation of a frame, showing every report read so far
+    pub fn frame_presented(&mut self, presented_at: std::time::Instant) {
+        if let Some(read_at) = self.pending_report_at.take() {
+            self.latency
+                .push(presented_at.saturating_duration_since(read_at));
+        }
+    }
+
+    /// Prints the percentiles of the measurements
+    pub fn print(&self) {
+        for (name, samples) in [
+            ("Latency", &self.latency),
+            ("Report interval", &self.report_interval),
+        ] {
+            println!(
+                "{name}: {} ({} samples, max {})",
+                samples.summary(),
+                samples.count(),
+                samples.max().map_or_else(|| "-".into(), format_duration),
+            );
+        }
+
+        if let Some(jitter) = self.report_interval.std_dev() {
+            println!("Jitter: {}", format_duration(jitter));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> std::time::Duration {
+        std::time::Duration::from_millis(ms)
+    }
+
+    fn samples(capacity: Option<usize>, values: impl IntoIterator<Item = u64>) -> Samples {
+        let mut samples = Samples::new(capacity);
+        for value in values {
+            samples.push(ms(value));
+        }
+        samples
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        // Pushed out of order, as they are sorted first
+        let samples = samples(None, [50, 10, 30, 20, 40]);
+
+        assert_eq!(
+            samples.percentiles([0., 20., 21., 50., 80., 81., 100.]),
+            Some([ms(10), ms(10), ms(20), ms(30), ms(40), ms(50), ms(50)])
+        );
+    }
+
+    #[test]
+    fn percentiles_of_a_single_sample() {
+        let samples = samples(None, [7]);
+
+        assert_eq!(samples.percentiles(PERCENTILES), Some([ms(7); 4]));
+    }
+
+    #[test]
+    fn percentiles_of_no_samples() {
+        let samples = Samples::unbounded();
+
+        assert_eq!(samples.percentiles(PERCENTILES), None);
+        assert_eq!(samples.summary(), "no samples");
+        assert_eq!(samples.mean(), None);
+        assert_eq!(samples.std_dev(), None);
+    }
+
+    #[test]
+    fn keeps_the_latest_samples() {
+        let samples = samples(Some(3), [100, 1, 2, 3]);
+
+        assert_eq!(samples.count(), 3);
+        assert_eq!(samples.max(), Some(ms(3)));
+        assert_eq!(samples.percentiles([100.]), Some([ms(3)]));
+    }
+
+    #[test]
+    fn statistics() {
+        let samples = samples(None, [2, 4, 4, 4, 5, 5, 7, 9]);
+
+        assert_eq!(samples.mean(), Some(ms(5)));
+        assert_eq!(samples.std_dev(), Some(ms(2)));
+        // 9ms is above the maximum, so it falls in the last bucket
+        assert_eq!(samples.histogram(4, ms(8)), [0, 1, 5, 2]);
+        assert!(samples.histogram(0, ms(8)).is_empty());
+    }
+
+    #[test]
+    fn latency_is_measured_from_the_first_unshown_change() {
+        let start = std::time::Instant::now();
+        let mut monitor = LatencyMonitor::unbounded();
+
+        monitor.report_read(start);
+        monitor.report_read(start + ms(1));
+        monitor.state_changed(start + ms(1));
+        monitor.report_read(start + ms(2));
+        monitor.state_changed(start + ms(2));
+        monitor.frame_presented(start + ms(5));
+        // Nothing changed since the last frame
+        monitor.frame_presented(start + ms(6));
+
+        assert_eq!(monitor.latency.percentiles([100.]), Some([ms(4)]));
+        assert_eq!(monitor.report_interval.count(), 2);
+
+        monitor.disconnected();
+        monitor.report_read(start + ms(100));
+        assert_eq!(monitor.report_interval.count(), 2);
+    }
+}
@@ -0,0 +1,487 @@
+use crate::config::{DEFAULT_FILENAME, ViewerOptions};
+
+/// Prefix of the environment variables overriding settings, eg. `B0XX_VIEWER_DISPLAY_LABELS=true`
+pub const ENV_PREFIX: &str = "B0XX_VIEWER_";
+/// Name of the folder holding the configuration in the system and user configuration directories
+const CONFIG_DIR_NAME: &str = "b0xx-viewer";
+
+/// Source of a setting. Layers are listed from the lowest to the highest precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigLayer {
+    /// Built-in defaults
+    Defaults,
+    /// Bundled theme
+    Theme(String),
+    /// Configuration shared by every user of the system
+    System(std::path::PathBuf),
+    /// Configuration of the current user, in their configuration directory
+    User(std::path::PathBuf),
+    /// Configuration provided with `--config`, or next to the executable
+    File(std::path::PathBuf),
+    /// Environment variable
+    Environment(String),
+    /// Command line option
+    CommandLine,
+}
+
+impl ConfigLayer {
+    /// Path of the configuration file of the layer, if it's file-based
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            Self::System(path) | Self::User(path) | Self::File(path) => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Defaults => write!(f, "defaults"),
+            Self::Theme(name) => write!(f, "theme {name}"),
+            Self::System(path) => write!(f, "system config {}", path.display()),
+            Self::User(path) => write!(f, "user config {}", path.display()),
+            Self::File(path) => write!(f, "config file {}", path.display()),
+            Self::Environment(var) => write!(f, "environment variable {var}"),
+            Self::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// Location of the system-wide configuration file
+pub fn system_config_path() -> Option<std::path::PathBuf> {
+    #[cfg(windows)]
+    let dir = std::path::PathBuf::from(std::env::var_os("PROGRAMDATA")?);
+    #[cfg(not(windows))]
+    let dir = std::path::PathBuf::from("/etc");
+
+    Some(dir.join(CONFIG_DIR_NAME).join(DEFAULT_FILENAME))
+}
+
+/// Locations of the configuration file of the current user, by order of preference.
+/// They follow the XDG base directory specification, falling back on `~/.config`.
+pub fn user_config_paths() -> Vec<std::path::PathBuf> {
+    let non_empty = |var: &str| {
+        std::env::var_os(var)
+            .filter(|value| !value.is_empty())
+            .map(std::path::PathBuf::from)
+    };
+
+    #[cfg(windows)]
+    let dirs = [non_empty("APPDATA")];
+    #[cfg(not(windows))]
+    let dirs = [
+        non_empty("XDG_CONFIG_HOME"),
+        non_empty("HOME").map(|home| home.join(".config")),
+    ];
+
+    let mut paths: Vec<_> = dirs
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(CONFIG_DIR_NAME).join(DEFAULT_FILENAME))
+        .collect();
+    paths.dedup();
+    paths
+}
+
+/// Locations of a configuration file kept along with the viewer, by order of preference:
+/// next to the executable, then in the working directory
+pub fn local_config_paths() -> Vec<std::path::PathBuf> {
+    let mut paths: Vec<_> = [
+        std::env::current_exe().ok().map(|mut path| {
+            path.set_file_name(DEFAULT_FILENAME);
+            path
+        }),
+        std::env::current_dir()
+            .ok()
+            .map(|dir| dir.join(DEFAULT_FILENAME)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    paths.dedup();
+    paths
+}
+
+/// Returns the first of `paths` that exists
+pub fn find_config(paths: &[std::path::PathBuf]) -> Option<&std::path::PathBuf> {
+    paths.iter().find(|path| path.is_file())
+}
+
+/// Configuration built by merging layers on top of each other, keeping track of the layer that set each setting
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    /// Layers merged so far, from the lowest to the highest precedence
+    layers: Vec<ConfigLayer>,
+    table: toml::Table,
+    /// Layer of each setting, by dotted path. Settings missing from it come from the defaults.
+    sources: std::collections::BTreeMap<String, ConfigLayer>,
+}
+
+impl LayeredConfig {
+    pub fn new(defaults: &ViewerOptions) -> Result<Self, toml::ser::Error> {
+        let defaults = toml::Table::try_from(defaults)?;
+        Ok(Self {
+            layers: vec![ConfigLayer::Defaults],
+            table: defaults,
+            sources: Default::default(),
+        })
+    }
+
+    /// Merges `table` over the current settings. Tables are merged key by key, other values are replaced.
+    pub fn merge(&mut self, layer: ConfigLayer, table: toml::Table) {
+        merge_table(&mut self.table, table, "", &layer, &mut self.sources);
+        self.layers.push(layer);
+    }
+
+    /// Deserializes the merged settings
+    pub fn options(&self) -> Result<ViewerOptions, toml::de::Error> {
+        let mut table = self.table.clone();
+
+        // Profiles only override some of the button colors, the rest falls back on the colors of the configuration
+        let keys = ["button_active_colors", "button_inactive_colors"];
+        let base_colors = keys.map(|key| table.get(key).cloned());
+        if let Some(toml::Value::Table(profiles)) = table.get_mut("profiles") {
+            for profile in profiles
+                .iter_mut()
+                .filter_map(|(_, profile)| profile.as_table_mut())
+            {
+                for (key, base_colors) in keys.iter().zip(&base_colors) {
+                    let (Some(toml::Value::Table(colors)), Some(toml::Value::Table(base_colors))) =
+                        (profile.get_mut(*key), base_colors)
+                    else {
+                        continue;
+                    };
+
+                    for (button, color) in base_colors {
+                        colors.entry(button).or_insert_with(|| color.clone());
+                    }
+                }
+            }
+        }
+
+        toml::Value::Table(table).try_into()
+    }
+
+    /// Returns the layer that set the setting at the dotted `path`
+    pub fn source(&self, path: &str) -> &ConfigLayer {
+        let mut path = path;
+        loop {
+            if let Some(layer) = self.sources.get(path) {
+                return layer;
+            }
+
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None => return &ConfigLayer::Defaults,
+            }
+        }
+    }
+
+    /// Prints the effective value of every setting of `options`, along with the layer that set it
+    pub fn print(&self, options: &ViewerOptions) -> Result<(), toml::ser::Error> {
+        let table = toml::Table::try_from(options)?;
+        let mut settings = vec![];
+        flatten_table(&table, "", &mut settings);
+
+        println!("# Layers, from the lowest to the highest precedence:");
+        for layer in &self.layers {
+            println!("#   {layer}");
+        }
+
+        for (path, value) in settings {
+            println!("{path} = {value} # {}", self.source(&path));
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings set through environment variables, one layer per variable
+pub fn env_layers() -> Vec<(ConfigLayer, toml::Table)> {
+    let mut vars: Vec<_> = std::env::vars()
+        .filter(|(var, _)| var.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort();
+
+    vars.into_iter()
+        .filter_map(|(var, value)| {
+            let table = env_setting(&var, &value)?;
+            Some((ConfigLayer::Environment(var), table))
+        })
+        .collect()
+}
+
+fn merge_table(
+    into: &mut toml::Table,
+    from: toml::Table,
+    prefix: &str,
+    layer: &ConfigLayer,
+    sources: &mut std::collections::BTreeMap<String, ConfigLayer>,
+) {
+    for (key, value) in from {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        let toml::Value::Table(from) = value else {
+            // The value replaces whatever was set below it
+            let children = format!("{path}.");
+            sources.retain(|source, _| !source.starts_with(&children));
+            sources.insert(path, layer.clone());
+            into.insert(key, value);
+            continue;
+        };
+
+        if !into.get(&key).is_some_and(toml::Value::is_table) {
+            sources.remove(&path);
+            into.insert(key.clone(), toml::Value::Table(Default::default()));
+        }
+
+        if let Some(toml::Value::Table(into)) = into.get_mut(&key) {
+            merge_table(into, from, &path, layer, sources);
+        }
+    }
+}
+
+fn flatten_table(table: &toml::Table, prefix: &str, settings: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match value {
+            toml::Value::Table(table) => flatten_table(table, &path, settings),
+            value => settings.push((path, value.clone())),
+        }
+    }
+}
+
+/// Parses the setting of the environment variable `var`.
+/// Values are read as TOML values if they are valid for the setting, as strings otherwise.
+fn env_setting(var: &str, value: &str) -> Option<toml::Table> {
+    let key = var.trim_start_matches(ENV_PREFIX).to_lowercase();
+    let typed_value = format!("value = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+
+    let mut error = None;
+    for value in typed_value
+        .into_iter()
+        .chain(std::iter::once(toml::Value::String(value.to_string())))
+    {
+        let table = toml::Table::from_iter([(key.clone(), value)]);
+        let mut is_known = true;
+        let mut on_ignored = |_: serde_ignored::Path| is_known = false;
+        let deserializer =
+            serde_ignored::Deserializer::new(toml::Value::Table(table.clone()), &mut on_ignored);
+
+        match <ViewerOptions as serde::Deserialize>::deserialize(deserializer) {
+            Ok(_) if !is_known => {
+                log::warn!("{var}: unknown setting `{key}`");
+                return None;
+            }
+            Ok(_) => return Some(table),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    if let Some(e) = error {
+        log::warn!("{var}: invalid value for `{key}`: {}", e.message().trim());
+    }
+
+    None
+}
+
+/// Makes the paths of a configuration file layer absolute, as they are relative to the file
+pub fn absolutize_paths(table: &mut toml::Table, dir: &std::path::Path) {
+    let absolutize = |value: &mut toml::Value| {
+        if let toml::Value::String(path) = value {
+            *path = dir.join(&*path).to_string_lossy().into_owned();
+        }
+    };
+
+    if let Some(font) = table.get_mut("font") {
+        absolutize(font);
+    }
+
+    // `log_file` can also be a boolean, which is left as it is
+    if let Some(log_file) = table.get_mut("log_file") {
+        absolutize(log_file);
+    }
+
+    let Some(toml::Value::Table(skin)) = table.get_mut("skin") else {
+        return;
+    };
+
+    for key in ["background", "pressed", "unpressed"] {
+        if let Some(image) = skin.get_mut(key) {
+            absolutize(image);
+        }
+    }
+
+    let Some(toml::Value::Table(buttons)) = skin.get_mut("buttons") else {
+        return;
+    };
+
+    for button in buttons
+        .iter_mut()
+        .filter_map(|(_, button)| button.as_table_mut())
+    {
+        for (_, image) in button.iter_mut() {
+            absolutize(image);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::b0xx_state::B0xxButton;
+    use crate::colors::ViewerColor;
+
+    fn table(source: &str) -> toml::Table {
+        source.parse().unwrap()
+    }
+
+    fn color(color: &str) -> ViewerColor {
+        color.parse().unwrap()
+    }
+
+    fn user() -> ConfigLayer {
+        ConfigLayer::User("user.toml".into())
+    }
+
+    fn file() -> ConfigLayer {
+        ConfigLayer::File("file.toml".into())
+    }
+
+    #[test]
+    fn higher_layers_override_lower_ones() {
+        let mut layers = LayeredConfig::new(&ViewerOptions::default()).unwrap();
+        layers.merge(user(), table("scale = 2.0\ndisplay_labels = true"));
+        layers.merge(file(), table("scale = 3.0"));
+        layers.merge(ConfigLayer::CommandLine, table("display_labels = false"));
+
+        let options = layers.options().unwrap();
+        assert_eq!(options.scale, 3.);
+        assert!(!options.display_labels);
+        assert_eq!(layers.source("scale"), &file());
+        assert_eq!(layers.source("display_labels"), &ConfigLayer::CommandLine);
+        assert_eq!(layers.source("background_color"), &ConfigLayer::Defaults);
+    }
+
+    #[test]
+    fn tables_are_merged_key_by_key() {
+        let mut layers = LayeredConfig::new(&ViewerOptions::default()).unwrap();
+        layers.merge(user(), table("[button_active_colors]\na = \"#FF0000\""));
+        layers.merge(file(), table("[button_active_colors]\nb = \"#00FF00\""));
+
+        let options = layers.options().unwrap();
+        let colors = &options.button_active_colors;
+        assert_eq!(colors.get(B0xxButton::A), color("#FF0000"));
+        assert_eq!(colors.get(B0xxButton::B), color("#00FF00"));
+        assert_eq!(
+            colors.get(B0xxButton::X),
+            ViewerOptions::default()
+                .button_active_colors
+                .get(B0xxButton::X)
+        );
+        assert_eq!(layers.source("button_active_colors.a"), &user());
+        assert_eq!(layers.source("button_active_colors.b"), &file());
+        assert_eq!(
+            layers.source("button_active_colors.x"),
+            &ConfigLayer::Defaults
+        );
+    }
+
+    #[test]
+    fn profiles_fall_back_on_the_configured_colors() {
+        let mut layers = LayeredConfig::new(&ViewerOptions::default()).unwrap();
+        layers.merge(
+            user(),
+            table("[button_active_colors]\na = \"#FF0000\"\nb = \"#00FF00\""),
+        );
+        layers.merge(
+            file(),
+            table("[profiles.stream.button_active_colors]\nb = \"#0000FF\""),
+        );
+
+        let options = layers.options().unwrap().with_profile(Some("stream"));
+        let colors = &options.button_active_colors;
+        assert_eq!(colors.get(B0xxButton::A), color("#FF0000"));
+        assert_eq!(colors.get(B0xxButton::B), color("#0000FF"));
+        assert_eq!(
+            colors.get(B0xxButton::X),
+            ViewerOptions::default()
+                .button_active_colors
+                .get(B0xxButton::X)
+        );
+    }
+
+    #[test]
+    fn values_replace_the_settings_below_them() {
+        let mut layers = LayeredConfig::new(&ViewerOptions::default()).unwrap();
+        layers.merge(user(), table("[log_file]\npath = \"viewer.log\""));
+        layers.merge(file(), table("log_file = false"));
+
+        assert_eq!(layers.source("log_file"), &file());
+        assert_eq!(layers.source("log_file.path"), &file());
+    }
+
+    #[test]
+    fn layers_are_listed_by_precedence() {
+        let mut layers = LayeredConfig::new(&ViewerOptions::default()).unwrap();
+        layers.merge(file(), toml::Table::new());
+        layers.merge(ConfigLayer::CommandLine, toml::Table::new());
+
+        assert_eq!(
+            layers.layers,
+            [ConfigLayer::Defaults, file(), ConfigLayer::CommandLine]
+        );
+    }
+
+    #[test]
+    fn env_settings_are_typed() {
+        assert_eq!(
+            env_setting("B0XX_VIEWER_DISPLAY_LABELS", "true"),
+            Some(table("display_labels = true"))
+        );
+        assert_eq!(
+            env_setting("B0XX_VIEWER_SCALE", "1.5"),
+            Some(table("scale = 1.5"))
+        );
+        // Not valid TOML, so it's read as a string
+        assert_eq!(
+            env_setting("B0XX_VIEWER_BACKGROUND_COLOR", "#000000"),
+            Some(table("background_color = \"#000000\""))
+        );
+    }
+
+    #[test]
+    fn invalid_env_settings_are_rejected() {
+        assert_eq!(env_setting("B0XX_VIEWER_NOT_A_SETTING", "true"), None);
+        assert_eq!(env_setting("B0XX_VIEWER_SCALE", "big"), None);
+        assert_eq!(env_setting("B0XX_VIEWER_DISPLAY_LABELS", "maybe"), None);
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_file() {
+        let dir = std::path::Path::new("themes");
+        let mut layer = table(
+            "font = \"font.ttf\"\nlog_file = true\n[skin]\nbackground = \"bg.png\"\n[skin.buttons.a]\npressed = \"a.png\"",
+        );
+        absolutize_paths(&mut layer, dir);
+
+        let path = |path: &str| toml::Value::String(dir.join(path).to_string_lossy().into());
+        assert_eq!(layer["font"], path("font.ttf"));
+        assert_eq!(layer["log_file"], toml::Value::Boolean(true));
+        assert_eq!(layer["skin"]["background"], path("bg.png"));
+        assert_eq!(layer["skin"]["buttons"]["a"]["pressed"], path("a.png"));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::b0xx_state::B0xxButton;
+use crate::colors::ViewerColor;
+
+/// Position of each button's center, relative to the center of the reference 600x300 window
+pub const DEFAULT_LAYOUT: [(B0xxButton, f64, f64); 20] = [
+    (B0xxButton::L, -232., 33.),
+    (B0xxButton::Left, -192., 55.),
+    (B0xxButton::Down, -147., 60.),
+    (B0xxButton::Right, -105., 45.),
+    (B0xxButton::ModX, -100., -45.),
+    (B0xxButton::ModY, -62., -67.),
+    (B0xxButton::Start, 0., 40.),
+    (B0xxButton::B, 100., 45.),
+    (B0xxButton::X, 142., 60.),
+    (B0xxButton::Z, 185., 55.),
+    (B0xxButton::Up, 227., 37.),
+    (B0xxButton::R, 100., 87.),
+    (B0xxButton::Y, 142., 102.),
+    (B0xxButton::ModLs, 185., 97.),
+    (B0xxButton::ModMs, 225., 80.),
+    (B0xxButton::A, 90., -55.),
+    (B0xxButton::CUp, 91., -7.),
+    (B0xxButton::CLeft, 57., -31.),
+    (B0xxButton::CRight, 125., -31.),
+    (B0xxButton::CDown, 57., -79.),
+];
+
+/// Radius of the corners of rounded rectangles, in pixels before scaling
+pub const DEFAULT_CORNER_RADIUS: f64 = 8.;
+/// Thickness of the rims, in pixels before scaling
+pub const DEFAULT_RIM_THICKNESS: f64 = 2.;
+/// Segments approximating each rounded corner
+const CORNER_SEGMENTS: usize = 6;
+
+const fn default_shadow_size() -> f64 {
+    4.
+}
+
+/// Shape of a button
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ButtonShape {
+    #[default]
+    Circle,
+    /// Rectangle with its corners rounded by `corner_radius`
+    RoundedRectangle,
+    /// Rectangle with sharp corners, square unless `width` and `height` differ
+    Square,
+    Hexagon,
+    /// Points of a polygon, eg. `{ polygon = [[0, 1], [1, -1], [-1, -1]] }`, stretched to the size of the button
+    Polygon(Vec<[f64; 2]>),
+}
+
+impl ButtonShape {
+    /// Returns the points of the shape fit in a `width` x `height` box centered on the origin, or `None` for circles.
+    /// Polygons of less than 3 points are drawn as circles.
+    pub fn points(&self, width: f64, height: f64, corner_radius: f64) -> Option<Vec<[f64; 2]>> {
+        let (half_w, half_h) = (width / 2., height / 2.);
+        let points = match self {
+            Self::Circle => return None,
+            Self::Polygon(points) if points.len() < 3 => return None,
+            Self::Square => vec![
+                [-half_w, -half_h],
+                [half_w, -half_h],
+                [half_w, half_h],
+                [-half_w, half_h],
+            ],
+            Self::RoundedRectangle => {
+                let radius = corner_radius.clamp(0., half_w.min(half_h));
+                let corners = [
+                    (half_w - radius, half_h - radius),
+                    (radius - half_w, half_h - radius),
+                    (radius - half_w, radius - half_h),
+                    (half_w - radius, radius - half_h),
+                ];
+                corners
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(i, (x, y))| {
+                        (0..=CORNER_SEGMENTS).map(move |segment| {
+                            let angle = std::f64::consts::FRAC_PI_2
+                                * (i as f64 + segment as f64 / CORNER_SEGMENTS as f64);
+                            [x + radius * angle.cos(), y + radius * angle.sin()]
+                        })
+                    })
+                    .collect()
+            }
+            Self::Hexagon => (0..6)
+                .map(|i| {
+                    let angle = std::f64::consts::FRAC_PI_3 * i as f64;
+                    [half_w * angle.cos(), half_h * angle.sin()]
+                })
+                .collect(),
+            Self::Polygon(points) => {
+                let (min_x, max_x, min_y, max_y) = points.iter().fold(
+                    (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+                    |(min_x, max_x, min_y, max_y), [x, y]| {
+                        (min_x.min(*x), max_x.max(*x), min_y.min(*y), max_y.max(*y))
+                    },
+                );
+                let stretch = |value: f64, min: f64, max: f64, half: f64| {
+                    if max > min {
+                        ((value - min) / (max - min) * 2. - 1.) * half
+                    } else {
+                        0.
+                    }
+                };
+                points
+                    .iter()
+                    .map(|[x, y]| {
+                        [
+                            stretch(*x, min_x, max_x, half_w),
+                            stretch(*y, min_y, max_y, half_h),
+                        ]
+                    })
+                    .collect()
+            }
+        };
+
+        Some(points)
+    }
+}
+
+/// Shadow cast inside of a button, along its edges
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerInnerShadow {
+    /// In pixels before scaling
+    #[serde(default = "default_shadow_size")]
+    pub size: f64,
+    #[serde(default = "ViewerColor::shadow_default")]
+    pub color: ViewerColor,
+}
+
+/// Appearance of a button. Missing settings keep their default value
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerButtonStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shape: Option<ButtonShape>,
+    /// In pixels before scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+    /// In pixels before scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+    /// Radius of the corners of rounded rectangles, in pixels before scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corner_radius: Option<f64>,
+    /// Thickness of the rims drawn with `colored_rims` and `pressed_rim_color`, in pixels before scaling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rim_thickness: Option<f64>,
+    /// Color of the rim drawn around pressed buttons. Pressed buttons have no rim by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressed_rim_color: Option<ViewerColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_shadow: Option<ViewerInnerShadow>,
+}
+
+impl ViewerButtonStyle {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fills the missing settings with the ones from `other`
+    pub fn merge_defaults(&mut self, other: &Self) {
+        if self.shape.is_none() {
+            self.shape.clone_from(&other.shape);
+        }
+        self.width = self.width.or(other.width);
+        self.height = self.height.or(other.height);
+        self.corner_radius = self.corner_radius.or(other.corner_radius);
+        self.rim_thickness = self.rim_thickness.or(other.rim_thickness);
+        self.pressed_rim_color = self.pressed_rim_color.or(other.pressed_rim_color);
+        self.inner_shadow = self.inner_shadow.or(other.inner_shadow);
+    }
+}
+
+/// Position of a button's center, along with its style. Missing coordinates keep their default value
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerButtonPosition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f64>,
+    /// Overrides the style of the layout for this button
+    #[serde(default, skip_serializing_if = "ViewerButtonStyle::is_empty")]
+    pub style: ViewerButtonStyle,
+}
+
+/// Button layout, overriding the default B0XX one.
+/// Coordinates are in pixels relative to the center of a 600x300 window, with Y pointing up.
+///
+/// ```toml
+/// [layout.style]
+/// shape = "rounded-rectangle"
+/// pressed_rim_color = "#FFFFFF"
+///
+/// [layout.buttons.a]
+/// x = 90
+/// y = -55
+/// style = { shape = "hexagon", width = 50 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerLayout {
+    /// Style of every button, unless overriden in `buttons`
+    #[serde(default, skip_serializing_if = "ViewerButtonStyle::is_empty")]
+    pub style: ViewerButtonStyle,
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub buttons: std::collections::BTreeMap<B0xxButton, ViewerButtonPosition>,
+}
+
+impl ViewerLayout {
+    pub fn is_empty(&self) -> bool {
+        self.style.is_empty() && self.buttons.is_empty()
+    }
+
+    /// Returns the style of a button, falling back on the style of the layout
+    pub fn style(&self, button: B0xxButton) -> ViewerButtonStyle {
+        let mut style = self
+            .buttons
+            .get(&button)
+            .map(|position| position.style.clone())
+            .unwrap_or_default();
+        style.merge_defaults(&self.style);
+        style
+    }
+
+    pub fn position(&self, button: B0xxButton) -> (f64, f64) {
+        let (_, default_x, default_y) = DEFAULT_LAYOUT
+            .iter()
+            .copied()
+            .find(|(b, ..)| *b == button)
+            .unwrap_or((button, 0., 0.));
+
+        match self.buttons.get(&button) {
+            Some(position) => (
+                position.x.unwrap_or(default_x),
+                position.y.unwrap_or(default_y),
+            ),
+            None => (default_x, default_y),
+        }
+    }
+}
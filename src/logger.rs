@@ -1,5 +1,134 @@
 use fern::colors::{Color, ColoredLevelConfig};
 
+/// Output that every log record goes through, replaced once the configuration is known
+static OUTPUT: std::sync::RwLock<Option<Box<dyn log::Log>>> = std::sync::RwLock::new(None);
+
+/// Verbosity of the log
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => Self::Off,
+            LogLevel::Error => Self::Error,
+            LogLevel::Warn => Self::Warn,
+            LogLevel::Info => Self::Info,
+            LogLevel::Debug => Self::Debug,
+            LogLevel::Trace => Self::Trace,
+        }
+    }
+}
+
+/// Format of the log records
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Destination of the log file. In configuration files, `log_file = false` disables it,
+/// and `log_file = "<path>"` writes it at `<path>` instead of next to the executable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LogFile {
+    /// `<executable>.log`
+    #[default]
+    Default,
+    Disabled,
+    Path(std::path::PathBuf),
+}
+
+impl LogFile {
+    /// Path of the log file, if enabled
+    pub fn path(&self) -> Option<std::path::PathBuf> {
+        match self {
+            Self::Default => {
+                let mut path = std::env::current_exe().ok()?;
+                let _ = path.set_extension("log");
+                Some(path)
+            }
+            Self::Disabled => None,
+            Self::Path(path) => Some(path.clone()),
+        }
+    }
+}
+
+impl serde::Serialize for LogFile {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Default => serializer.serialize_bool(true),
+            Self::Disabled => serializer.serialize_bool(false),
+            Self::Path(path) => path.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for LogFile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum RawLogFile {
+            Enabled(bool),
+            Path(std::path::PathBuf),
+        }
+
+        Ok(match RawLogFile::deserialize(deserializer)? {
+            RawLogFile::Enabled(true) => Self::Default,
+            RawLogFile::Enabled(false) => Self::Disabled,
+            RawLogFile::Path(path) => Self::Path(path),
+        })
+    }
+}
+
+/// Logging settings, from the configuration
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub level: LogLevel,
+    pub file: LogFile,
+    pub format: LogFormat,
+    /// Size from which the log file is rotated, in kilobytes. 0 disables the rotation
+    pub max_size: u64,
+    /// Number of rotated log files kept along with the current one
+    pub max_files: u32,
+}
+
+/// Size from which the log file is rotated by default, in kilobytes
+pub const DEFAULT_LOG_MAX_SIZE: u64 = 10 * 1024;
+pub const DEFAULT_LOG_MAX_FILES: u32 = 3;
+
 #[derive(Debug)]
 pub struct Logger {
     has_init: bool,
@@ -10,22 +139,140 @@ impl Logger {
         Self { has_init: false }
     }
 
+    /// Starts logging to stdout. The log file is only opened by `configure`, once the configuration is loaded.
     pub fn init(&mut self) {
         if self.has_init {
             return;
         }
 
-        fern::Dispatch::new()
-            .chain(stdout())
-            .chain(filelog())
-            .apply()
-            .unwrap();
+        log::set_boxed_logger(Box::new(ProxyLog)).unwrap();
+        replace_output(
+            dispatch(LogLevel::default(), &RustLog::from_env())
+                .chain(stdout(LogFormat::default()))
+                .into_log(),
+        );
 
         self.has_init = true;
     }
+
+    /// Applies the logging settings of the configuration, `RUST_LOG` overriding the log level.
+    /// If the log file can't be opened, logs only go to stdout.
+    pub fn configure(&mut self, options: &LogOptions) {
+        self.init();
+
+        let rust_log = RustLog::from_env();
+        let mut output = dispatch(options.level, &rust_log).chain(stdout(options.format));
+        let mut file_error = None;
+        if let Some(path) = options.file.path() {
+            match RotatingFile::open(
+                &path,
+                options.max_size.saturating_mul(1024),
+                options.max_files,
+            ) {
+                Ok(file) => output = output.chain(filelog(options.format, file)),
+                Err(e) => file_error = Some((path, e)),
+            }
+        }
+
+        replace_output(output.into_log());
+        for directive in &rust_log.invalid {
+            log::warn!("Ignoring the invalid RUST_LOG directive `{directive}`");
+        }
+
+        if let Some((path, e)) = file_error {
+            log::error!(
+                "Could not open the log file {}, logging to stdout only: {e}",
+                path.display()
+            );
+        }
+    }
 }
 
-fn stdout() -> fern::Dispatch {
+/// Logs through the current output, so that it can be replaced after the logger is installed
+struct ProxyLog;
+
+impl log::Log for ProxyLog {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        let output = OUTPUT.read().unwrap_or_else(|e| e.into_inner());
+        output
+            .as_ref()
+            .is_some_and(|output| output.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        let output = OUTPUT.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(output) = output.as_ref() {
+            output.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        let output = OUTPUT.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(output) = output.as_ref() {
+            output.flush();
+        }
+    }
+}
+
+fn replace_output((level, output): (log::LevelFilter, Box<dyn log::Log>)) {
+    *OUTPUT.write().unwrap_or_else(|e| e.into_inner()) = Some(output);
+    log::set_max_level(level);
+}
+
+/// Log levels set with the `RUST_LOG` environment variable, which take precedence over the configuration,
+/// eg. `RUST_LOG=debug` or `RUST_LOG=trace,glium=info`
+#[derive(Debug, Default)]
+struct RustLog {
+    /// Level of the viewer
+    level: Option<log::LevelFilter>,
+    targets: Vec<(String, log::LevelFilter)>,
+    /// Directives that couldn't be parsed, and are ignored
+    invalid: Vec<String>,
+}
+
+impl RustLog {
+    fn from_env() -> Self {
+        std::env::var("RUST_LOG")
+            .map(|directives| Self::parse(&directives))
+            .unwrap_or_default()
+    }
+
+    fn parse(directives: &str) -> Self {
+        let mut rust_log = Self::default();
+        for directive in directives.split(',').map(str::trim) {
+            let parsed = match directive.split_once('=') {
+                _ if directive.is_empty() => continue,
+                Some((target, level)) => level
+                    .parse()
+                    .map(|level| rust_log.targets.push((target.to_string(), level))),
+                None => directive.parse().map(|level| rust_log.level = Some(level)),
+            };
+
+            if parsed.is_err() {
+                rust_log.invalid.push(directive.to_string());
+            }
+        }
+
+        rust_log
+    }
+}
+
+/// Logs the viewer at `level`, and its dependencies only when they warn, unless `RUST_LOG` says otherwise
+fn dispatch(level: LogLevel, rust_log: &RustLog) -> fern::Dispatch {
+    let level = rust_log.level.unwrap_or(level.into());
+    let dispatch = fern::Dispatch::new()
+        .level(level.min(log::LevelFilter::Warn))
+        .level_for(env!("CARGO_CRATE_NAME"), level);
+
+    rust_log
+        .targets
+        .iter()
+        .fold(dispatch, |dispatch, (target, level)| {
+            dispatch.level_for(target.clone(), *level)
+        })
+}
+
+fn stdout(format: LogFormat) -> fern::Dispatch {
     let colors = ColoredLevelConfig::new()
         .error(Color::Red)
         .warn(Color::Yellow)
@@ -35,30 +282,105 @@ fn stdout() -> fern::Dispatch {
         .info(Color::Green);
 
     fern::Dispatch::new()
-        .format(move |out, message, record| {
-            out.finish(format_args!(
+        .format(move |out, message, record| match format {
+            LogFormat::Text => out.finish(format_args!(
                 "[{target}] {level} > {message}",
                 level = colors.color(record.level()),
                 target = record.target(),
-            ))
+            )),
+            LogFormat::Json => out.finish(format_args!("{}", json_record(message, record))),
         })
-        .level(log::LevelFilter::Info)
         .chain(std::io::stdout())
 }
 
-fn filelog() -> fern::Dispatch {
-    let mut logpath = std::env::current_exe().unwrap();
-    let _ = logpath.set_extension("log");
-
+fn filelog(format: LogFormat, file: RotatingFile) -> fern::Dispatch {
     fern::Dispatch::new()
-        .format(|out, message, record| {
-            out.finish(format_args!(
+        .format(move |out, message, record| match format {
+            LogFormat::Text => out.finish(format_args!(
                 "[{time}][{level}][{target}] > {message}",
                 time = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
                 level = record.level(),
                 target = record.target(),
-            ))
+            )),
+            LogFormat::Json => out.finish(format_args!("{}", json_record(message, record))),
+        })
+        .chain(Box::new(file) as Box<dyn std::io::Write + Send>)
+}
+
+/// Formats a record as a single-line JSON object
+fn json_record(message: &std::fmt::Arguments, record: &log::Record) -> String {
+    serde_json::json!({
+        "time": chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": message.to_string(),
+    })
+    .to_string()
+}
+
+/// Log file that is renamed to `<path>.1` once it reaches its maximum size,
+/// the previous rotated files being shifted to `<path>.2`, `<path>.3`...
+struct RotatingFile {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+    size: u64,
+    /// 0 disables the rotation
+    max_size: u64,
+    max_files: u32,
+}
+
+impl RotatingFile {
+    fn open(path: &std::path::Path, max_size: u64, max_files: u32) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: file.metadata()?.len(),
+            file,
+            max_size,
+            max_files,
         })
-        .level(log::LevelFilter::Debug)
-        .chain(fern::log_file(logpath).unwrap())
+    }
+
+    fn rotated_path(&self, index: u32) -> std::path::PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            self.file.set_len(0)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        *self = Self::open(&self.path, self.max_size, self.max_files)?;
+        Ok(())
+    }
+}
+
+impl std::io::Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.max_size > 0 && self.size >= self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
 }
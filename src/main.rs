@@ -1,32 +1,41 @@
 #![cfg_attr(not(feature = "win_console"), windows_subsystem = "windows")]
 
+mod animations;
 mod b0xx_state;
+mod benchmark;
 mod colors;
 mod config;
+mod diagnostics;
 mod error;
+mod fake_inputs;
+mod labels;
+mod latency;
+mod layers;
+mod layout;
 mod logger;
+mod migrations;
+mod profile;
 mod serial_probe;
+mod skin;
+mod themes;
 mod ui;
+mod web_theme;
 
 pub use self::error::*;
 
 pub fn main() -> ViewerResult<()> {
-    if let Ok(env) = std::env::var("RUST_LOG") {
-        unsafe { std::env::set_var("RUST_LOG", format!("b0xx_viewer=info,{env}")) };
-    } else {
-        unsafe { std::env::set_var("RUST_LOG", "b0xx_viewer=info") };
-    }
-
     let mut logger = logger::Logger::new();
     logger.init();
 
-    let Some(options) = config::ViewerOptions::run()? else {
-        std::process::exit(0);
+    let options = match config::ViewerOptions::run() {
+        Ok(Some(options)) => options,
+        Ok(None) => std::process::exit(0),
+        // The problems were already reported
+        Err(ViewerError::ConfigCheckFailed(_)) => std::process::exit(1),
+        Err(e) => return Err(e),
     };
+    logger.configure(&options.log_options());
 
-    let rx = serial_probe::start_serial_probe(&options.custom_tty)?;
-
-    log::info!("Serial probe up and running");
-    ui::start_gui(rx, options);
-    Ok(())
+    let fake_inputs = options.fake_inputs()?;
+    ui::start_gui(options, fake_inputs)
 }
@@ -0,0 +1,169 @@
+use crate::ViewerOptionConfigError;
+
+/// Version of the configuration format written by this version of the viewer
+pub const CONFIG_VERSION: i64 = 2;
+/// Version of the configuration files that don't have a `version` field
+const UNVERSIONED_CONFIG_VERSION: i64 = 1;
+
+/// Upgrade of the configuration format to `version`, from the version before it
+struct Migration {
+    version: i64,
+    migrate: fn(&mut toml_edit::DocumentMut),
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 2,
+    migrate: migrate_v2,
+}];
+
+/// Upgrades a configuration file to the current format, keeping its formatting and comments.
+/// Returns the version the configuration was upgraded from, or `None` if it was already up to date.
+pub fn migrate(
+    document: &mut toml_edit::DocumentMut,
+) -> Result<Option<i64>, ViewerOptionConfigError> {
+    let version = document
+        .get("version")
+        .and_then(toml_edit::Item::as_integer)
+        .unwrap_or(UNVERSIONED_CONFIG_VERSION);
+
+    if version > CONFIG_VERSION {
+        return Err(ViewerOptionConfigError::UnsupportedVersion(version));
+    }
+
+    let before = document.to_string();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        log::debug!(
+            "Migrating the configuration to version {}",
+            migration.version
+        );
+        (migration.migrate)(document);
+    }
+
+    // Unversioned configurations that are already in the current format are left as they are
+    let is_migrated = document.to_string() != before;
+    if version == CONFIG_VERSION || !(is_migrated || document.contains_key("version")) {
+        return Ok(None);
+    }
+
+    document["version"] = toml_edit::value(CONFIG_VERSION);
+    Ok(Some(version))
+}
+
+/// Upgrades a configuration from before v0.7.0:
+/// * Colors were `[r, g, b]` arrays instead of hex strings
+/// * The `is_r2_b0xx` setting was replaced by `is_r1_b0xx`, as the r2 layout became the default
+fn migrate_v2(document: &mut toml_edit::DocumentMut) {
+    migrate_array_colors(document.as_table_mut(), false);
+
+    if let Some(is_r2_b0xx) = document
+        .remove("is_r2_b0xx")
+        .and_then(|item| item.as_bool())
+        && !document.contains_key("is_r1_b0xx")
+    {
+        document["is_r1_b0xx"] = toml_edit::value(!is_r2_b0xx);
+    }
+}
+
+/// Replaces the `[r, g, b]` colors of the table with hex strings.
+/// Colors are the settings ending with `color`, and every setting of the tables ending with `colors`.
+fn migrate_array_colors(table: &mut dyn toml_edit::TableLike, is_color_table: bool) {
+    for (key, item) in table.iter_mut() {
+        let is_color = is_color_table || key.get().ends_with("color");
+        if let Some(child) = item.as_table_like_mut() {
+            migrate_array_colors(child, key.get().ends_with("colors"));
+            continue;
+        }
+
+        let Some(hex) = item.as_array().filter(|_| is_color).and_then(array_to_hex) else {
+            continue;
+        };
+
+        let decor = item.as_value().map(|value| value.decor().clone());
+        *item = toml_edit::value(hex);
+        if let (Some(value), Some(decor)) = (item.as_value_mut(), decor) {
+            *value.decor_mut() = decor;
+        }
+    }
+}
+
+/// Converts an `[r, g, b]` or `[r, g, b, a]` array to a hex color
+fn array_to_hex(array: &toml_edit::Array) -> Option<String> {
+    let components = array
+        .iter()
+        .map(|value| value.as_integer().and_then(|c| u8::try_from(c).ok()))
+        .collect::<Option<Vec<_>>>()?;
+
+    match components[..] {
+        [r, g, b] => Some(format!("#{r:02X}{g:02X}{b:02X}")),
+        [r, g, b, a] => Some(format!("#{r:02X}{g:02X}{b:02X}{a:02X}")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate_str(source: &str) -> (Result<Option<i64>, ViewerOptionConfigError>, String) {
+        let mut document = source.parse::<toml_edit::DocumentMut>().unwrap();
+        let result = migrate(&mut document);
+        (result, document.to_string())
+    }
+
+    #[test]
+    fn upgrades_unversioned_configurations() {
+        let (result, migrated) = migrate_str(
+            "is_r2_b0xx = true\nbackground_color = [19, 19, 19] # dark\n\n[button_active_colors]\na = [0, 235, 255, 128]\n",
+        );
+
+        assert_eq!(result.unwrap(), Some(1));
+        assert_eq!(
+            migrated,
+            "background_color = \"#131313\" # dark\nis_r1_b0xx = false\nversion = 2\n\n[button_active_colors]\na = \"#00EBFF80\"\n"
+        );
+    }
+
+    #[test]
+    fn keeps_is_r1_b0xx_when_both_are_set() {
+        let (result, migrated) = migrate_str("is_r2_b0xx = true\nis_r1_b0xx = true\n");
+
+        assert_eq!(result.unwrap(), Some(1));
+        assert_eq!(migrated, "is_r1_b0xx = true\nversion = 2\n");
+    }
+
+    #[test]
+    fn leaves_current_configurations_untouched() {
+        for source in [
+            "version = 2\nbackground_color = \"#131313\"\n",
+            // Unversioned, but already in the current format
+            "background_color = \"#131313\"\n",
+        ] {
+            let (result, migrated) = migrate_str(source);
+            assert_eq!(result.unwrap(), None);
+            assert_eq!(migrated, source);
+        }
+    }
+
+    #[test]
+    fn only_converts_color_arrays() {
+        let (_, migrated) = migrate_str(
+            "version = 1\nwindow_size = [1, 2, 3]\nbackground_color = [256, 0, 0]\ntap_color = [1, 2]\n",
+        );
+
+        assert_eq!(
+            migrated,
+            "version = 2\nwindow_size = [1, 2, 3]\nbackground_color = [256, 0, 0]\ntap_color = [1, 2]\n"
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let (result, migrated) = migrate_str("version = 3\n");
+
+        assert!(matches!(
+            result,
+            Err(ViewerOptionConfigError::UnsupportedVersion(3))
+        ));
+        assert_eq!(migrated, "version = 3\n");
+    }
+}
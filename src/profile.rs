@@ -0,0 +1,74 @@
+use crate::{
+    animations::ViewerAnimations,
+    colors::*,
+    config::ViewerOptions,
+    labels::{LabelSet, ViewerButtonLabels},
+    layout::ViewerLayout,
+};
+
+/// Named set of appearance settings, overriding the top-level ones of the configuration.
+///
+/// ```toml
+/// default_profile = "gcc"
+///
+/// [profiles.gcc]
+/// background_color = "#131313"
+/// colored_rims = true
+///
+/// [profiles.gcc.button_active_colors]
+/// a = "#29c7be"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<ViewerColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub button_active_colors: Option<ViewerButtonColors>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub button_inactive_colors: Option<ViewerButtonColors>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colored_rims: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_labels: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_set: Option<LabelSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<ViewerButtonLabels>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<ViewerLayout>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animations: Option<ViewerAnimations>,
+}
+
+impl ViewerProfile {
+    /// Overrides the settings of `options` with the ones set in this profile
+    pub fn apply(&self, options: &mut ViewerOptions) {
+        if let Some(color) = self.background_color {
+            options.background_color = color;
+        }
+        if let Some(colors) = self.button_active_colors {
+            options.button_active_colors = colors;
+        }
+        if let Some(colors) = self.button_inactive_colors {
+            options.button_inactive_colors = colors;
+        }
+        if let Some(colored_rims) = self.colored_rims {
+            options.colored_rims = colored_rims;
+        }
+        if let Some(display_labels) = self.display_labels {
+            options.display_labels = display_labels;
+        }
+        if let Some(label_set) = self.label_set {
+            options.label_set = label_set;
+        }
+        if let Some(labels) = &self.labels {
+            options.labels.clone_from(labels);
+        }
+        if let Some(layout) = &self.layout {
+            options.layout.clone_from(layout);
+        }
+        if let Some(animations) = self.animations {
+            options.animations = animations;
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::b0xx_state::*;
 use crate::error::{ViewerError, ViewerResult};
+use crate::fake_inputs::{FakeInputs, REPORT_INTERVAL};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
@@ -50,34 +51,339 @@ lazy_static! {
     };
 }
 
-#[cfg_attr(feature = "fake_inputs", allow(dead_code))]
+/// Longest delay between two attempts to connect to the B0XX
+const RETRY_MAX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// Interval at which the UI is notified while waiting for a B0XX, keeping the retry countdown up to date
+const WAITING_NOTIFY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+/// Interval at which the statistics of the connection are sent to the UI
+const STATS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Number of read times kept in the mailbox while the viewer doesn't pick the reports up
+const MAILBOX_MAX_READ_TIMES: usize = 1024;
+
+/// Events of the connection. The state of the buttons goes through `PendingReports` instead
 #[derive(Debug)]
 pub enum B0xxMessage {
-    State(B0xxState),
-    Error(ViewerError),
-    Reconnect,
-    Quit,
+    /// A B0XX was found and its port opened
+    Connected(DeviceInfo),
+    /// Statistics of the current connection, sent every `STATS_INTERVAL`
+    Stats(ConnectionStats),
+    /// No B0XX is connected, the next connection attempt is scheduled
+    Waiting(WaitingStatus),
 }
 
-#[inline]
-pub fn reconnect(custom_tty: &Option<String>) -> crossbeam_channel::Receiver<B0xxMessage> {
-    use backoff::backoff::Backoff as _;
-    let mut backoff = backoff::ExponentialBackoff::default();
-    loop {
-        if let Ok(new_rx) = start_serial_probe(custom_tty) {
-            return new_rx;
+/// Identity of the connected B0XX
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub port_name: String,
+    /// USB vendor and product IDs, if the port is a USB one
+    pub usb_id: Option<(u16, u16)>,
+    pub product: Option<String>,
+    pub connected_at: std::time::Instant,
+    /// Number of times the connection was established again since the viewer started
+    pub reconnections: u32,
+}
+
+impl DeviceInfo {
+    fn new(port: &serialport::SerialPortInfo, reconnections: u32) -> Self {
+        let (usb_id, product) = match &port.port_type {
+            serialport::SerialPortType::UsbPort(usb) => {
+                (Some((usb.vid, usb.pid)), usb.product.clone())
+            }
+            _ => (None, None),
+        };
+
+        Self {
+            port_name: port.port_name.clone(),
+            usb_id,
+            product,
+            connected_at: std::time::Instant::now(),
+            reconnections,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConnectionStats {
+    pub reports_per_sec: f64,
+    /// Reports that were cut or held unexpected bytes since the connection was established
+    pub malformed_reports: u64,
+}
+
+/// Counts the reports read from the B0XX, to compute the statistics of the connection
+#[derive(Debug)]
+struct ReportCounter {
+    reports: u32,
+    malformed_reports: u64,
+    since: std::time::Instant,
+}
+
+impl ReportCounter {
+    fn new() -> Self {
+        Self {
+            reports: 0,
+            malformed_reports: 0,
+            since: std::time::Instant::now(),
+        }
+    }
+
+    /// Counts a report, returning the statistics once every `STATS_INTERVAL`
+    fn count(&mut self, is_malformed: bool) -> Option<ConnectionStats> {
+        self.reports += 1;
+        if is_malformed {
+            self.malformed_reports += 1;
+        }
+
+        let elapsed = self.since.elapsed();
+        if elapsed < STATS_INTERVAL {
+            return None;
+        }
+
+        let stats = ConnectionStats {
+            reports_per_sec: self.reports as f64 / elapsed.as_secs_f64(),
+            malformed_reports: self.malformed_reports,
+        };
+        self.reports = 0;
+        self.since = std::time::Instant::now();
+        Some(stats)
+    }
+}
+
+/// State of the connection while waiting for a B0XX
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitingStatus {
+    /// Why the last connection attempt failed, or why the connection was lost
+    pub error: String,
+    /// When the next connection attempt happens
+    pub retry_at: std::time::Instant,
+}
+
+impl WaitingStatus {
+    /// Seconds left before the next connection attempt, rounded up
+    pub fn retry_in_secs(&self) -> u64 {
+        let remaining = self
+            .retry_at
+            .saturating_duration_since(std::time::Instant::now());
+        remaining.as_millis().div_ceil(1000) as u64
+    }
+}
+
+/// Reports read since the viewer last picked them up
+#[derive(Debug, Clone)]
+pub struct PendingReports {
+    /// Latest state of the buttons
+    pub state: B0xxState,
+    /// Buttons pressed in any of the reports, so that taps released before the pickup aren't lost
+    pub pressed: B0xxState,
+    /// Number of reports read
+    pub count: usize,
+    /// Read time of the latest `MAILBOX_MAX_READ_TIMES` reports, oldest first
+    pub read_times: std::collections::VecDeque<std::time::Instant>,
+    /// Read time of the first report that changed the state of the buttons, if any did
+    pub changed_at: Option<std::time::Instant>,
+}
+
+/// Wakes the viewer up, returning false once it's closed
+pub type Waker = Box<dyn FnMut() -> bool + Send>;
+
+/// Messages of the serial thread waiting for the viewer, which is woken up when the mailbox stops being empty.
+/// Only the latest state, statistics and waiting status are kept, so that neither side can stall the other.
+#[derive(Clone)]
+pub struct Mailbox(std::sync::Arc<std::sync::Mutex<MailboxContent>>);
+
+struct MailboxContent {
+    messages: Vec<B0xxMessage>,
+    reports: Option<PendingReports>,
+    /// Latest state posted, to tell which reports change it
+    last_state: B0xxState,
+    wake: Waker,
+    is_closed: bool,
+}
+
+impl MailboxContent {
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.reports.is_none()
+    }
+
+    /// Wakes the viewer up if the mailbox was empty, returning false once it's closed
+    fn notify(&mut self, was_empty: bool) -> bool {
+        if was_empty && !self.is_closed {
+            self.is_closed = !(self.wake)();
+        }
+        !self.is_closed
+    }
+}
+
+impl Mailbox {
+    fn new(wake: Waker) -> Self {
+        Self(std::sync::Arc::new(std::sync::Mutex::new(MailboxContent {
+            messages: vec![],
+            reports: None,
+            last_state: B0xxState::default(),
+            wake,
+            is_closed: false,
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MailboxContent> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Posts the state of a report read at `read_at`, replacing the previous one if it wasn't picked up yet.
+    /// Returns false once the viewer is closed.
+    fn post_state(&self, state: B0xxState, read_at: std::time::Instant) -> bool {
+        let mut content = self.lock();
+        let was_empty = content.is_empty();
+        let last_state = std::mem::replace(&mut content.last_state, state);
+        let reports = content.reports.get_or_insert_with(|| PendingReports {
+            state,
+            pressed: state,
+            count: 0,
+            read_times: Default::default(),
+            changed_at: None,
+        });
+
+        if state != last_state {
+            reports.changed_at.get_or_insert(read_at);
+        }
+        reports.state = state;
+        reports.pressed = reports.pressed.union(&state);
+        reports.count += 1;
+        if reports.read_times.len() >= MAILBOX_MAX_READ_TIMES {
+            reports.read_times.pop_front();
         }
+        reports.read_times.push_back(read_at);
 
-        if let Some(backoff_duration) = backoff.next_backoff() {
-            std::thread::sleep(backoff_duration);
+        content.notify(was_empty)
+    }
+
+    /// Posts an event of the connection, replacing the previous statistics or waiting status.
+    /// Returns false once the viewer is closed.
+    fn post(&self, message: B0xxMessage) -> bool {
+        let mut content = self.lock();
+        let was_empty = content.is_empty();
+        match &message {
+            B0xxMessage::Connected(_) => {}
+            B0xxMessage::Stats(_) => content
+                .messages
+                .retain(|message| !matches!(message, B0xxMessage::Stats(_))),
+            B0xxMessage::Waiting(_) => {
+                content
+                    .messages
+                    .retain(|message| !matches!(message, B0xxMessage::Waiting(_)));
+                // The reports read before the connection was lost are outdated
+                content.reports = None;
+            }
         }
+        content.messages.push(message);
+
+        content.notify(was_empty)
+    }
+
+    /// Takes the events of the connection and the reports that are waiting, for the viewer
+    pub fn take(&self) -> (Vec<B0xxMessage>, Option<PendingReports>) {
+        let mut content = self.lock();
+        (
+            std::mem::take(&mut content.messages),
+            content.reports.take(),
+        )
+    }
+
+    /// Stops the serial thread, once the viewer is closed
+    pub fn close(&self) {
+        self.lock().is_closed = true;
     }
 }
 
-#[cfg(not(feature = "fake_inputs"))]
-pub fn start_serial_probe(
+/// Connects to the B0XX in the background, retrying until one is found and whenever the connection is lost.
+/// The state of the device and the connection attempts are left in the returned mailbox, `wake` being called when it stops being empty.
+/// With fake inputs, they are played instead of looking for a B0XX.
+pub fn connect(
+    custom_tty: Option<String>,
+    fake_inputs: Option<FakeInputs>,
+    wake: Waker,
+) -> ViewerResult<Mailbox> {
+    use backoff::backoff::Backoff as _;
+    let mailbox = Mailbox::new(wake);
+    let tx = mailbox.clone();
+
+    std::thread::Builder::new()
+        .name("b0xx_viewer_connection".into())
+        .spawn(move || {
+            let mut backoff = backoff::ExponentialBackoff {
+                max_interval: RETRY_MAX_INTERVAL,
+                max_elapsed_time: None,
+                ..Default::default()
+            };
+            let mut last_error = String::new();
+            let mut connections = 0;
+
+            loop {
+                let serial_thread = match &fake_inputs {
+                    Some(fake_inputs) => start_fake_inputs(fake_inputs, connections, tx.clone()),
+                    None => start_serial_probe(&custom_tty, connections, tx.clone()),
+                };
+                let result = match serial_thread {
+                    Ok(serial_thread) => {
+                        backoff.reset();
+                        connections += 1;
+                        serial_thread.join().unwrap_or_else(|panic| {
+                            let reason = panic
+                                .downcast_ref::<&str>()
+                                .copied()
+                                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                                .unwrap_or("unknown reason");
+                            log::error!("The serial thread panicked: {reason}");
+                            Err(ViewerError::UnknownError)
+                        })
+                    }
+                    Err(e) => Err(e),
+                };
+
+                // The viewer was closed
+                let Err(e) = result else {
+                    return;
+                };
+
+                let error = e.user_message();
+                if error != last_error {
+                    log::error!("{e}");
+                    last_error.clone_from(&error);
+                }
+
+                let status = WaitingStatus {
+                    error,
+                    retry_at: std::time::Instant::now()
+                        + backoff.next_backoff().unwrap_or(RETRY_MAX_INTERVAL),
+                };
+                log::debug!("Retrying to connect in {}s", status.retry_in_secs());
+
+                while std::time::Instant::now() < status.retry_at {
+                    if !tx.post(B0xxMessage::Waiting(status.clone())) {
+                        return;
+                    }
+
+                    std::thread::sleep(
+                        WAITING_NOTIFY_INTERVAL.min(
+                            status
+                                .retry_at
+                                .saturating_duration_since(std::time::Instant::now()),
+                        ),
+                    );
+                }
+            }
+        })?;
+
+    Ok(mailbox)
+}
+
+/// Thread reading the reports of the B0XX. It returns `Ok` once the viewer is closed, or the error that broke the connection.
+type SerialThread = std::thread::JoinHandle<ViewerResult<()>>;
+
+fn start_serial_probe(
     custom_tty: &Option<String>,
-) -> ViewerResult<crossbeam_channel::Receiver<B0xxMessage>> {
+    reconnections: u32,
+    tx: Mailbox,
+) -> ViewerResult<SerialThread> {
     let b0xx_port = serialport::available_ports()?
         .into_iter()
         .find(move |port| {
@@ -113,66 +419,48 @@ pub fn start_serial_probe(
 
     log::info!("Found B0XX on port {}", b0xx_port.port_name);
 
-    let (tx, rx) = crossbeam_channel::bounded(1);
+    // The port is opened before starting the thread, so that a port that can't be opened isn't counted as a connection
+    let mut port = serialport::new(&b0xx_port.port_name, 115_200)
+        .data_bits(serialport::DataBits::Eight)
+        .flow_control(serialport::FlowControl::Hardware)
+        .parity(serialport::Parity::None)
+        .stop_bits(serialport::StopBits::One)
+        .timeout(std::time::Duration::from_millis(500))
+        .open()?;
 
-    std::thread::Builder::new()
+    exhaust_buffer(&mut port)?;
+
+    let serial_thread = std::thread::Builder::new()
         .name("b0xx_viewer_serial".into())
         .spawn(move || {
             let mut buf = Vec::with_capacity(25);
             let mut state = [B0xxReport::default(); 20];
 
-            let port_builder = serialport::new(&b0xx_port.port_name, 115_200)
-                .data_bits(serialport::DataBits::Eight)
-                .flow_control(serialport::FlowControl::Hardware)
-                .parity(serialport::Parity::None)
-                .stop_bits(serialport::StopBits::One)
-                .timeout(std::time::Duration::from_millis(500));
-
-            let mut port = match port_builder.open() {
-                Ok(port) => port,
-                Err(e) => return tx.send(B0xxMessage::Error(e.into())),
-            };
+            if !tx.post(B0xxMessage::Connected(DeviceInfo::new(
+                &b0xx_port,
+                reconnections,
+            ))) {
+                return Ok(());
+            }
 
-            exhaust_buffer(&mut port, &tx);
+            let mut counter = ReportCounter::new();
 
             let mut port = std::io::BufReader::with_capacity(25, port);
 
             use std::io::BufRead as _;
             loop {
-                if let Err(e) = port.get_mut().write_request_to_send(true) {
-                    return tx.send(B0xxMessage::Error(e.into()));
-                }
+                port.get_mut().write_request_to_send(true)?;
 
-                let bytes_read: usize = match port
-                    .read_until(B0xxReport::End as u8, &mut buf)
-                    .map_err(Into::into)
-                {
-                    Ok(bytes) => bytes,
-                    Err(e) => match &e {
-                        ViewerError::IoError(io_error) => match io_error.kind() {
-                            std::io::ErrorKind::TimedOut | std::io::ErrorKind::BrokenPipe => {
-                                return tx.send(B0xxMessage::Reconnect);
-                            }
-                            _ => {
-                                log::error!("{e:?}");
-                                return tx.send(B0xxMessage::Quit);
-                            }
-                        },
-                        _ => {
-                            log::error!("{e:?}");
-                            return tx.send(B0xxMessage::Quit);
-                        }
-                    },
-                };
+                let bytes_read = port.read_until(B0xxReport::End as u8, &mut buf)?;
+                let read_at = std::time::Instant::now();
 
-                if let Err(e) = port.get_mut().write_request_to_send(false) {
-                    return tx.send(B0xxMessage::Error(e.into()));
-                }
+                port.get_mut().write_request_to_send(false)?;
 
                 log::trace!("Bytes read: {bytes_read}");
 
                 port.consume(bytes_read);
-                if bytes_read == 25 {
+                let mut is_malformed = bytes_read != 25;
+                if !is_malformed {
                     let end_index = buf
                         .iter()
                         .position(|item| *item == B0xxReport::End as u8)
@@ -184,50 +472,94 @@ pub fn start_serial_probe(
                     for i in start_index..end_index {
                         state[i] = buf[i].into();
                     }
+
+                    is_malformed = state.contains(&B0xxReport::Invalid);
                 } else {
-                    exhaust_buffer(port.get_mut(), &tx);
+                    exhaust_buffer(port.get_mut())?;
                 }
 
                 buf.clear();
 
-                if tx.send(B0xxMessage::State(state.into())).is_err() {
-                    log::info!("Reconnection detected, exiting runloop");
+                if !tx.post_state(state.into(), read_at) {
+                    log::info!("Viewer closed, exiting runloop");
+                    return Ok(());
+                }
+
+                if let Some(stats) = counter.count(is_malformed)
+                    && !tx.post(B0xxMessage::Stats(stats))
+                {
                     return Ok(());
                 }
             }
         })?;
 
-    Ok(rx)
+    Ok(serial_thread)
 }
 
-#[cfg(feature = "fake_inputs")]
-pub fn start_serial_probe(
-    _: &Option<String>,
-) -> ViewerResult<crossbeam_channel::Receiver<B0xxMessage>> {
-    let (tx, rx) = crossbeam_channel::bounded(1);
-    if std::env::var("RELAX_ARDUINO_DETECT").is_ok() {
-        log::info!("{:#?}", *ARDUINO_WHITELIST)
-    }
-    use rand::SeedableRng as _;
-    let mut rng = rand::rngs::SmallRng::from_entropy();
-    let sleep_dur = std::time::Duration::from_micros(8700);
-    std::thread::spawn(move || {
-        loop {
-            let _ = tx.send(B0xxMessage::State(B0xxState::random(&mut rng)));
-            #[cfg(not(feature = "benchmark"))]
-            std::thread::sleep(sleep_dur);
-        }
-    });
+/// Plays the fake inputs in place of the reports of a B0XX
+fn start_fake_inputs(
+    fake_inputs: &FakeInputs,
+    reconnections: u32,
+    tx: Mailbox,
+) -> ViewerResult<SerialThread> {
+    let port = serialport::SerialPortInfo {
+        port_name: fake_inputs.name(),
+        port_type: serialport::SerialPortType::Unknown,
+    };
+    let steps = fake_inputs.steps();
+    let is_throttled = fake_inputs.is_throttled();
+
+    let serial_thread = std::thread::Builder::new()
+        .name("b0xx_viewer_fake_inputs".into())
+        .spawn(move || {
+            if !tx.post(B0xxMessage::Connected(DeviceInfo::new(
+                &port,
+                reconnections,
+            ))) {
+                return Ok(());
+            }
+
+            let mut counter = ReportCounter::new();
+            let mut next_report_at = std::time::Instant::now();
+            for step in steps {
+                // Every step is reported at least once, even if it's shorter than a report interval
+                let step_end = next_report_at + step.duration;
+                loop {
+                    let read_at = std::time::Instant::now();
+                    if !tx.post_state(step.state, read_at) {
+                        log::info!("Viewer closed, exiting runloop");
+                        return Ok(());
+                    }
 
-    Ok(rx)
+                    if let Some(stats) = counter.count(false)
+                        && !tx.post(B0xxMessage::Stats(stats))
+                    {
+                        return Ok(());
+                    }
+
+                    if !is_throttled {
+                        break;
+                    }
+
+                    // Catches up with the schedule when a report was late, instead of bursting
+                    next_report_at = (next_report_at + REPORT_INTERVAL).max(read_at);
+                    std::thread::sleep(
+                        next_report_at.saturating_duration_since(std::time::Instant::now()),
+                    );
+                    if next_report_at >= step_end {
+                        break;
+                    }
+                }
+            }
+
+            Ok(())
+        })?;
+
+    Ok(serial_thread)
 }
 
-#[allow(dead_code)]
 #[inline(always)]
-fn exhaust_buffer(
-    port: &mut Box<dyn serialport::SerialPort>,
-    tx: &crossbeam_channel::Sender<B0xxMessage>,
-) {
+fn exhaust_buffer(port: &mut Box<dyn serialport::SerialPort>) -> ViewerResult<()> {
     // Exhaust the initial buffer till we find the end of a report and consume it.
     // This is caused by a UB in Windows' COM port handling causing partial reports
     // sometimes
@@ -235,14 +567,7 @@ fn exhaust_buffer(
     let mut exhaust_buffer = [0u8; 1];
     use std::io::Read as _;
     loop {
-        if let Err(e) = port
-            .read_exact(&mut exhaust_buffer)
-            .map_err(ViewerError::from)
-        {
-            log::error!("{:?}", e);
-            let _ = tx.send(B0xxMessage::Quit);
-            break;
-        }
+        port.read_exact(&mut exhaust_buffer)?;
 
         if exhaust_buffer[0] == B0xxReport::End as u8 {
             log::trace!("Buffer exhausted successfully, continuing...");
@@ -250,7 +575,6 @@ fn exhaust_buffer(
         }
     }
 
-    if let Err(e) = port.clear(serialport::ClearBuffer::All) {
-        let _ = tx.send(B0xxMessage::Error(e.into()));
-    }
+    port.clear(serialport::ClearBuffer::All)?;
+    Ok(())
 }
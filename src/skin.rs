@@ -0,0 +1,78 @@
+use crate::b0xx_state::B0xxButton;
+
+/// Images of a button, for each of its states
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerButtonSkin {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressed: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unpressed: Option<std::path::PathBuf>,
+}
+
+impl ViewerButtonSkin {
+    /// Fills the missing images with the ones from `other`
+    pub fn merge_defaults(&mut self, other: &Self) {
+        if self.pressed.is_none() {
+            self.pressed.clone_from(&other.pressed);
+        }
+        if self.unpressed.is_none() {
+            self.unpressed.clone_from(&other.unpressed);
+        }
+    }
+}
+
+/// Image-based skin. Paths are relative to the configuration file they're declared in.
+///
+/// ```toml
+/// [skin]
+/// background = "background.png"
+/// pressed = "button_pressed.png"
+/// unpressed = "button.png"
+///
+/// [skin.buttons.a]
+/// pressed = "a_pressed.png"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ViewerSkin {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<std::path::PathBuf>,
+    /// Image applied to every pressed button, unless overriden in `buttons`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressed: Option<std::path::PathBuf>,
+    /// Image applied to every unpressed button, unless overriden in `buttons`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unpressed: Option<std::path::PathBuf>,
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub buttons: std::collections::BTreeMap<B0xxButton, ViewerButtonSkin>,
+}
+
+impl ViewerSkin {
+    pub fn is_empty(&self) -> bool {
+        self.background.is_none()
+            && self.pressed.is_none()
+            && self.unpressed.is_none()
+            && self.buttons.is_empty()
+    }
+
+    /// Returns the images of a button, falling back on the default button images
+    pub fn button(&self, button: B0xxButton) -> ViewerButtonSkin {
+        let mut skin = self.buttons.get(&button).cloned().unwrap_or_default();
+        skin.merge_defaults(&ViewerButtonSkin {
+            pressed: self.pressed.clone(),
+            unpressed: self.unpressed.clone(),
+        });
+        skin
+    }
+
+    /// Returns every image path referenced by the skin
+    pub fn paths(&self) -> impl Iterator<Item = &std::path::Path> {
+        [&self.background, &self.pressed, &self.unpressed]
+            .into_iter()
+            .chain(
+                self.buttons
+                    .values()
+                    .flat_map(|skin| [&skin.pressed, &skin.unpressed]),
+            )
+            .filter_map(|path| path.as_deref())
+    }
+}
@@ -0,0 +1,113 @@
+/// Theme embedded in the binary, used as the base of the configuration with `--theme` or `theme = "<name>"`
+#[derive(Debug, Clone, Copy)]
+pub struct BundledTheme {
+    pub name: &'static str,
+    pub description: &'static str,
+    source: &'static str,
+}
+
+pub const THEMES: &[BundledTheme] = &[
+    BundledTheme {
+        name: "gcc",
+        description: "GameCube controller palette",
+        source: include_str!("../assets/themes/gcc.toml"),
+    },
+    BundledTheme {
+        name: "high-contrast",
+        description: "White rims on black, pressed buttons in bright yellow",
+        source: include_str!("../assets/themes/high_contrast.toml"),
+    },
+    BundledTheme {
+        name: "colorblind-safe",
+        description: "Okabe-Ito palette, telling buttons apart with every form of color blindness",
+        source: include_str!("../assets/themes/colorblind_safe.toml"),
+    },
+    BundledTheme {
+        name: "fox",
+        description: "Melee: Fox's default palette",
+        source: include_str!("../assets/themes/fox.toml"),
+    },
+    BundledTheme {
+        name: "falco",
+        description: "Melee: Falco's default palette",
+        source: include_str!("../assets/themes/falco.toml"),
+    },
+    BundledTheme {
+        name: "marth",
+        description: "Melee: Marth's default palette",
+        source: include_str!("../assets/themes/marth.toml"),
+    },
+    BundledTheme {
+        name: "sheik",
+        description: "Melee: Sheik's default palette",
+        source: include_str!("../assets/themes/sheik.toml"),
+    },
+    BundledTheme {
+        name: "peach",
+        description: "Melee: Peach's default palette",
+        source: include_str!("../assets/themes/peach.toml"),
+    },
+    BundledTheme {
+        name: "jigglypuff",
+        description: "Melee: Jigglypuff's default palette",
+        source: include_str!("../assets/themes/jigglypuff.toml"),
+    },
+    BundledTheme {
+        name: "captain-falcon",
+        description: "Melee: Captain Falcon's default palette",
+        source: include_str!("../assets/themes/captain_falcon.toml"),
+    },
+];
+
+impl BundledTheme {
+    /// Looks up a bundled theme by name
+    pub fn find(name: &str) -> Option<&'static Self> {
+        THEMES
+            .iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Settings of the theme, to be merged as a configuration layer
+    pub fn table(&self) -> Result<toml::Table, toml::de::Error> {
+        toml::from_str(self.source)
+    }
+
+    /// Prints the name and description of every bundled theme
+    pub fn list() {
+        for theme in THEMES {
+            println!("{:16} {}", theme.name, theme.description);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_themes_are_valid_configurations() {
+        for theme in THEMES {
+            assert!(theme.table().is_ok(), "{}", theme.name);
+            if let Err(e) = toml::from_str::<crate::config::ViewerOptions>(theme.source) {
+                panic!("{}: {e}", theme.name);
+            }
+
+            let diagnostics =
+                crate::diagnostics::check_config(std::path::Path::new(theme.name), theme.source);
+            assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        }
+    }
+
+    #[test]
+    fn finds_themes_by_name() {
+        for theme in THEMES {
+            assert_eq!(BundledTheme::find(theme.name).unwrap().name, theme.name);
+        }
+
+        assert_eq!(
+            BundledTheme::find("High-Contrast").unwrap().name,
+            "high-contrast"
+        );
+        assert!(BundledTheme::find("high_contrast").is_none());
+    }
+}
@@ -1,17 +1,20 @@
-use crate::b0xx_state::B0xxState;
+use super::images::SkinImages;
+use crate::{
+    b0xx_state::{B0xxButton, B0xxState},
+    config::ViewerOptions,
+    labels::LabelSet,
+    latency::LatencyMonitor,
+    serial_probe::{ConnectionStats, DeviceInfo, WaitingStatus},
+};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub enum ViewerAppStatus {
+    /// Looking for a B0XX for the first time
+    #[default]
+    Connecting,
     Running,
-    Reconnecting,
-    NeedsReconnection,
-    Undefined,
-}
-
-impl Default for ViewerAppStatus {
-    fn default() -> Self {
-        ViewerAppStatus::Undefined
-    }
+    /// No B0XX is connected, waiting for the next connection attempt
+    Waiting(WaitingStatus),
 }
 
 impl ViewerAppStatus {
@@ -20,26 +23,190 @@ impl ViewerAppStatus {
             *self = ViewerAppStatus::Running;
         }
     }
+
+    /// Message displayed over the buttons while no B0XX is connected
+    pub fn overlay_message(&self) -> Option<String> {
+        match self {
+            Self::Connecting => Some("Looking for a B0XX…".into()),
+            Self::Running => None,
+            Self::Waiting(status) => Some(format!(
+                "Waiting for B0XX…\n{}\nRetrying in {}s",
+                status.error,
+                status.retry_in_secs()
+            )),
+        }
+    }
+}
+
+/// Connected B0XX along with the latest statistics of the connection
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub device: DeviceInfo,
+    pub stats: ConnectionStats,
+}
+
+impl ConnectionInfo {
+    /// Describes the connection for the info bar
+    pub fn summary(&self) -> String {
+        let device = &self.device;
+        let mut identity = device.port_name.clone();
+        if let Some((vid, pid)) = device.usb_id {
+            identity.push_str(&format!(" · {vid:04X}:{pid:04X}"));
+        }
+        if let Some(product) = &device.product {
+            identity.push_str(&format!(" · {product}"));
+        }
+
+        let uptime = device.connected_at.elapsed().as_secs();
+        format!(
+            "{identity}\nUp {:02}:{:02}:{:02} · {} reconnection{} · {:.0} reports/s · {} malformed",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60,
+            device.reconnections,
+            if device.reconnections == 1 { "" } else { "s" },
+            self.stats.reports_per_sec,
+            self.stats.malformed_reports,
+        )
+    }
+}
+
+/// Crossfade in progress between two profiles
+#[derive(Debug, Clone)]
+pub struct ProfileTransition {
+    /// Options of the profile being switched from
+    pub from: ViewerOptions,
+    pub started_at: std::time::Instant,
+}
+
+/// Last press and release of a button, which its animations start from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ButtonPress {
+    pub pressed_at: Option<std::time::Instant>,
+    pub released_at: Option<std::time::Instant>,
+}
+
+impl ButtonPress {
+    /// Returns when the released button stops being shown as pressed, no sooner than `min_duration` after it was pressed
+    pub fn shown_released_at(
+        &self,
+        min_duration: std::time::Duration,
+    ) -> Option<std::time::Instant> {
+        let released_at = self.released_at?;
+        Some(match self.pressed_at {
+            Some(pressed_at) => released_at.max(pressed_at + min_duration),
+            None => released_at,
+        })
+    }
 }
 
 #[derive(Debug, Default)]
 #[cfg_attr(not(feature = "fps"), derive(Clone))]
 pub struct ViewerApp {
     pub state: B0xxState,
+    /// Indexed like `B0xxButton::ALL`
+    pub presses: [ButtonPress; B0xxButton::ALL.len()],
+    /// Whether the last frame showed an animation in progress, so that another one shows its end
+    pub was_animating: bool,
     pub status: ViewerAppStatus,
     pub is_draggable: bool,
     pub is_dragged: bool,
+    pub skin: SkinImages,
+    /// Custom font of the labels, if any. The theme's font is used otherwise
+    pub label_font: Option<conrod_core::text::font::Id>,
+    pub label_set: LabelSet,
+    pub profile: Option<String>,
+    pub profile_transition: Option<ProfileTransition>,
+    /// Connected B0XX, if any
+    pub connection: Option<ConnectionInfo>,
+    pub show_connection_info: bool,
+    pub show_latency_histogram: bool,
+    pub latency: LatencyMonitor,
+    /// Error of the last configuration reload, displayed until the configuration is fixed
+    pub config_error: Option<String>,
     #[cfg(feature = "fps")]
     pub fps: fps_counter::FPSCounter,
 }
 
 impl ViewerApp {
-    pub fn update_state(&mut self, new_state: B0xxState) -> bool {
-        if self.state == new_state {
-            return false;
+    /// Updates the state of the buttons. `pressed` holds every button pressed since the last update,
+    /// so that the buttons pressed then released in the meantime are shown as tapped.
+    pub fn update_state(&mut self, new_state: B0xxState, pressed: B0xxState) -> bool {
+        let now = std::time::Instant::now();
+        let mut has_changed = false;
+        for button in B0xxButton::ALL {
+            let press = &mut self.presses[button.index()];
+            match (
+                self.state.is_pressed(button),
+                pressed.is_pressed(button),
+                new_state.is_pressed(button),
+            ) {
+                (false, _, true) => press.pressed_at = Some(now),
+                (true, _, false) => press.released_at = Some(now),
+                (false, true, false) => {
+                    press.pressed_at = Some(now);
+                    press.released_at = Some(now);
+                }
+                _ => continue,
+            }
+            has_changed = true;
         }
 
         self.state = new_state;
-        true
+        has_changed
+    }
+
+    pub fn button_press(&self, button: B0xxButton) -> ButtonPress {
+        self.presses[button.index()]
+    }
+
+    /// Returns true while a button is fading out, glowing or popping, or is released but still shown as pressed
+    pub fn is_animating(&self, options: &ViewerOptions, now: std::time::Instant) -> bool {
+        let animations = &options.animations;
+        B0xxButton::ALL.into_iter().any(|button| {
+            let press = self.button_press(button);
+            let released_at = press
+                .shown_released_at(options.min_press_duration())
+                .filter(|_| !self.state.is_pressed(button));
+            let is_tapped = released_at.is_some_and(|released_at| released_at > now);
+
+            let fade_out = animations
+                .fade_out
+                .and_then(|fade_out| fade_out.progress(released_at, now));
+            let glow = animations
+                .glow
+                .and_then(|glow| glow.animation().progress(press.pressed_at, now));
+            let pop = animations
+                .pop
+                .and_then(|pop| pop.animation().progress(press.pressed_at, now));
+            is_tapped || fade_out.or(glow).or(pop).is_some()
+        })
+    }
+
+    pub fn switch_profile(&mut self, name: String, from: &ViewerOptions, to: &ViewerOptions) {
+        self.profile = Some(name);
+        self.label_set = to.label_set;
+        self.profile_transition = Some(ProfileTransition {
+            from: from.clone(),
+            started_at: std::time::Instant::now(),
+        });
+    }
+
+    /// Returns the options of the previous profile and the progress of the crossfade, if it's still running
+    pub fn profile_transition_progress(
+        &mut self,
+        duration: std::time::Duration,
+    ) -> Option<(&ViewerOptions, f32)> {
+        let elapsed = self.profile_transition.as_ref()?.started_at.elapsed();
+        if elapsed >= duration {
+            self.profile_transition = None;
+            return None;
+        }
+
+        let transition = self.profile_transition.as_ref()?;
+        Some((
+            &transition.from,
+            elapsed.as_secs_f32() / duration.as_secs_f32(),
+        ))
     }
 }
@@ -1,6 +1,14 @@
 use super::{Ids, app::*};
-use crate::config::ViewerOptions;
-use crate::ui::support::{BTN_RADIUS, WIN_H, WIN_W};
+use crate::b0xx_state::B0xxButton;
+use crate::config::{LabelAlign, ViewerOptions};
+use crate::latency::format_duration;
+use crate::layout::{ButtonShape, DEFAULT_CORNER_RADIUS, DEFAULT_RIM_THICKNESS};
+use crate::ui::support::{BTN_RADIUS, layout_scale};
+
+/// Histograms of the latency panel: the latency added by the viewer, and the interval between reports
+pub const LATENCY_HISTOGRAMS: usize = 2;
+/// Bars of each latency histogram
+pub const LATENCY_BUCKETS: usize = 24;
 
 pub fn theme() -> conrod_core::Theme {
     use conrod_core::position::{Align, Direction, Padding, Position, Relative};
@@ -32,436 +40,276 @@ pub fn render_gui(
 ) {
     use conrod_core::{Colorable, Positionable, Sizeable, Widget, widget};
 
-    // Compute button margin only if necessary
-    let btn_label_margin = if options.display_labels {
-        BTN_RADIUS / 2. - ui.theme().font_size_small as f64
-    } else {
-        0.
-    };
+    let (win_w, win_h) = (ui.win_w, ui.win_h);
+    let scale = layout_scale(win_w, win_h);
+    let font_size = scale_font_size(ui.theme().font_size_medium, scale);
+    let label_font_size = scale_font_size(options.label_font_size, scale);
 
+    // The background color is applied when clearing the frame so that its
+    // alpha channel reaches the compositor untouched
     widget::Canvas::new()
-        .color(options.background_color.into())
-        .w_h(WIN_W.into(), WIN_H.into())
+        .color(conrod_core::color::TRANSPARENT)
+        .w_h(win_w, win_h)
         .x_y(0., 0.)
         .crop_kids()
         .set(ids.frame, ui);
 
-    if app.status == ViewerAppStatus::Reconnecting
-        || app.status == ViewerAppStatus::NeedsReconnection
-    {
-        conrod_core::widget::Rectangle::fill_with(
-            [WIN_W.into(), WIN_H.into()],
-            conrod_core::color::BLACK.with_alpha(0.8),
-        )
-        .w_h(WIN_W.into(), WIN_H.into())
-        .x_y(0., 0.)
-        .crop_kids()
-        .set(ids.reconnect_bg, ui);
-
-        conrod_core::widget::Text::new("Reconnecting...")
-            .color(conrod_core::color::WHITE)
-            .middle_of(ids.reconnect_bg)
-            .set(ids.reconnect_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.start,
-        ids.frame,
-        options.button_active_colors.start,
-        options.button_inactive_colors.start,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y(0., 40.).set(ids.start_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("SRT")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.start_btn, btn_label_margin)
-            .set(ids.start_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.right,
-        ids.frame,
-        options.button_active_colors.right,
-        options.button_inactive_colors.right,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.start_btn, -105., 5.)
-        .set(ids.right_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("→")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.right_btn, btn_label_margin)
-            .set(ids.right_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.down,
-        ids.frame,
-        options.button_active_colors.down,
-        options.button_inactive_colors.down,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.right_btn, -42., 15.)
-        .set(ids.down_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("↓")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.down_btn, btn_label_margin)
-            .set(ids.down_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.left,
-        ids.frame,
-        options.button_active_colors.left,
-        options.button_inactive_colors.left,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.down_btn, -45., -5.)
-        .set(ids.left_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("←")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.left_btn, btn_label_margin)
-            .set(ids.left_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.l,
-        ids.frame,
-        options.button_active_colors.l,
-        options.button_inactive_colors.l,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.left_btn, -40., -22.)
-        .set(ids.l_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("L")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.l_btn, btn_label_margin)
-            .set(ids.l_label, ui);
+    if let Some(background) = app.skin.background {
+        widget::Image::new(background)
+            .w_h(win_w, win_h)
+            .middle_of(ids.frame)
+            .graphics_for(ids.frame)
+            .set(ids.background, ui);
     }
 
-    let (btn, mut m_text) = make_button(
-        app.state.mod_x,
-        ids.frame,
-        options.button_active_colors.mod_x,
-        options.button_inactive_colors.mod_x,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.right_btn, 5., -90.)
-        .set(ids.mod_x_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("MX")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.mod_x_btn, btn_label_margin)
-            .set(ids.mod_x_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.mod_y,
-        ids.frame,
-        options.button_active_colors.mod_y,
-        options.button_inactive_colors.mod_y,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.mod_x_btn, 38., -22.)
-        .set(ids.mod_y_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("MY")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.mod_y_btn, btn_label_margin)
-            .set(ids.mod_y_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.b,
-        ids.frame,
-        options.button_active_colors.b,
-        options.button_inactive_colors.b,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.start_btn, 100., 5.)
-        .set(ids.b_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("B")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.b_btn, btn_label_margin)
-            .set(ids.b_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.x,
-        ids.frame,
-        options.button_active_colors.x,
-        options.button_inactive_colors.x,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.b_btn, 42., 15.).set(ids.x_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("X")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.x_btn, btn_label_margin)
-            .set(ids.x_label, ui);
-    }
-
-    let (btn, mut m_text) = make_button(
-        app.state.z,
-        ids.frame,
-        options.button_active_colors.z,
-        options.button_inactive_colors.z,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.x_btn, 43., -5.).set(ids.z_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("Z")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.z_btn, btn_label_margin)
-            .set(ids.z_label, ui);
-    }
+    let now = std::time::Instant::now();
+    let animations = &options.animations;
+    for (i, button) in B0xxButton::ALL.iter().copied().enumerate() {
+        if options.is_r1_b0xx && button.is_r2_only() {
+            continue;
+        }
 
-    let (btn, mut m_text) = make_button(
-        app.state.up,
-        ids.frame,
-        options.button_active_colors.up,
-        options.button_inactive_colors.up,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.z_btn, 42., -18.)
-        .set(ids.up_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("↑")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.up_btn, btn_label_margin)
-            .set(ids.up_label, ui);
-    }
+        let (x, y) = options.layout.position(button);
+        let xy = [x * scale, y * scale];
+        let style = options.layout.style(button);
+        let shape = style.shape.unwrap_or_default();
+        let corner_radius = style.corner_radius.unwrap_or(DEFAULT_CORNER_RADIUS) * scale;
+        let rim_thickness = style.rim_thickness.unwrap_or(DEFAULT_RIM_THICKNESS) * scale;
+
+        let press = app.button_press(button);
+        // Short presses stay visible for at least `min_press_duration`
+        let released_at = press
+            .shown_released_at(options.min_press_duration())
+            .filter(|_| !app.state.is_pressed(button));
+        let pressed = app.state.is_pressed(button)
+            || released_at.is_some_and(|released_at| released_at > now);
+        let is_tapped = released_at
+            .zip(press.released_at)
+            .is_some_and(|(shown_released_at, released_at)| shown_released_at > released_at);
+        // Released buttons fade back to their inactive color
+        let fade_out = animations
+            .fade_out
+            .filter(|_| !pressed)
+            .and_then(|fade_out| fade_out.progress(released_at, now));
+        // Pressed buttons grow, then shrink back to their size
+        let pop_scale = animations
+            .pop
+            .and_then(|pop| {
+                let t = pop.animation().progress(press.pressed_at, now)?;
+                Some(1. + (pop.scale - 1.) * (1. - t as f64))
+            })
+            .unwrap_or(1.);
+        let geometry = ShapeGeometry {
+            shape: &shape,
+            dim: [
+                style.width.unwrap_or(BTN_RADIUS) * scale * pop_scale,
+                style.height.unwrap_or(BTN_RADIUS) * scale * pop_scale,
+            ],
+            corner_radius,
+            xy,
+        };
+
+        if let Some(glow) = animations.glow
+            && let Some(t) = glow.animation().progress(press.pressed_at, now)
+        {
+            let width = glow.width * scale;
+            let color = glow
+                .color
+                .unwrap_or_else(|| options.button_active_colors.get(button));
+            let color: conrod_core::Color = color.into();
+            // The ring spreads out from the rim of the button while fading out
+            geometry.inset(-width * (1. + t as f64)).set(
+                ui,
+                ids.button_glows[i],
+                ids.frame,
+                ShapeStyle::Outline(color.with_alpha(color.to_rgb().3 * (1. - t)), width),
+            );
+        }
 
-    let (btn, mut m_text) = make_button(
-        app.state.y,
-        ids.frame,
-        options.button_active_colors.y,
-        options.button_inactive_colors.y,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.x_btn, 0., 42.).set(ids.y_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("Y")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.y_btn, btn_label_margin)
-            .set(ids.y_label, ui);
-    }
+        let (shape_style, mut m_text) =
+            make_button(pressed, is_tapped, fade_out, button, options, rim_thickness);
+
+        let btn_id = if let Some(image) = app.skin.button(button, pressed) {
+            widget::Image::new(image)
+                .wh(geometry.dim)
+                .x_y(xy[0], xy[1])
+                .parent(ids.frame)
+                .graphics_for(ids.frame)
+                .set(ids.button_images[i], ui);
+            ids.button_images[i]
+        } else {
+            geometry.set(ui, ids.buttons[i], ids.frame, shape_style);
+
+            // Darkens the inside of the edges, as if the button was sunk into the case
+            if let Some(shadow) = style.inner_shadow {
+                let size = shadow.size * scale;
+                geometry.inset(size).set(
+                    ui,
+                    ids.button_shadows[i],
+                    ids.buttons[i],
+                    ShapeStyle::Outline(shadow.color.into(), size),
+                );
+            }
+
+            if let Some(rim_color) = style.pressed_rim_color.filter(|_| pressed) {
+                geometry.set(
+                    ui,
+                    ids.button_rims[i],
+                    ids.buttons[i],
+                    ShapeStyle::Outline(rim_color.into(), rim_thickness),
+                );
+            }
+
+            ids.buttons[i]
+        };
 
-    let (btn, mut m_text) = make_button(
-        app.state.r,
-        ids.frame,
-        options.button_active_colors.r,
-        options.button_inactive_colors.r,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.b_btn, 0., 42.).set(ids.r_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("R")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.r_btn, btn_label_margin)
-            .set(ids.r_label, ui);
+        if let Some(text_color) = m_text.take() {
+            let btn_label_margin =
+                geometry.dim[1] / 2. - scale_font_size(ui.theme().font_size_small, scale) as f64;
+            let text = options.labels.label(button, app.label_set);
+            let font_id = label_font(ui, app, text);
+            let label = conrod_core::widget::Text::new(text)
+                .color(text_color)
+                .font_size(label_font_size);
+
+            let label = match options.label_align {
+                LabelAlign::Top => label.mid_top_with_margin_on(btn_id, btn_label_margin),
+                LabelAlign::Middle => label.middle_of(btn_id),
+                LabelAlign::Bottom => label.mid_bottom_with_margin_on(btn_id, btn_label_margin),
+            };
+
+            match font_id {
+                Some(font_id) => label.font_id(font_id),
+                None => label,
+            }
+            .set(ids.labels[i], ui);
+        }
     }
 
-    let (btn, mut m_text) = make_button(
-        app.state.a,
-        ids.frame,
-        options.button_active_colors.a,
-        options.button_inactive_colors.a,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.b_btn, -10., -100.)
-        .set(ids.a_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("A")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.a_btn, btn_label_margin)
-            .set(ids.a_label, ui);
-    }
+    // Drawn over the buttons, as their state is stale
+    if let Some(message) = app.status.overlay_message() {
+        widget::Rectangle::fill_with([win_w, win_h], conrod_core::color::BLACK.with_alpha(0.8))
+            .x_y(0., 0.)
+            .parent(ids.frame)
+            .graphics_for(ids.frame)
+            .set(ids.status_bg, ui);
 
-    let (btn, mut m_text) = make_button(
-        app.state.c_up,
-        ids.frame,
-        options.button_active_colors.c_up,
-        options.button_inactive_colors.c_up,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.a_btn, 1., 48.)
-        .set(ids.c_up_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("CU")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.c_up_btn, btn_label_margin)
-            .set(ids.c_up_label, ui);
+        widget::Text::new(&message)
+            .color(conrod_core::color::WHITE)
+            .font_size(font_size)
+            .w(win_w - 40.)
+            .wrap_by_word()
+            .center_justify()
+            .middle_of(ids.status_bg)
+            .set(ids.status_label, ui);
     }
 
-    let (btn, mut m_text) = make_button(
-        app.state.c_left,
-        ids.frame,
-        options.button_active_colors.c_left,
-        options.button_inactive_colors.c_left,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.c_up_btn, -34., -24.)
-        .set(ids.c_left_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("CL")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.c_left_btn, btn_label_margin)
-            .set(ids.c_left_label, ui);
+    if let Some(connection) = app.connection.as_ref().filter(|_| app.show_connection_info) {
+        widget::Text::new(&connection.summary())
+            .color(conrod_core::color::LIGHT_GREY)
+            .font_size(scale_font_size(ui.theme().font_size_small, scale))
+            .top_left_with_margin_on(ids.frame, 10.)
+            .floating(true)
+            .set(ids.connection_info, ui);
     }
 
-    let (btn, mut m_text) = make_button(
-        app.state.c_right,
-        ids.frame,
-        options.button_active_colors.c_right,
-        options.button_inactive_colors.c_right,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.c_up_btn, 34., -24.)
-        .set(ids.c_right_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("CR")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.c_right_btn, btn_label_margin)
-            .set(ids.c_right_label, ui);
+    if app.show_latency_histogram {
+        latency_histograms(ui, ids, app, scale);
     }
 
-    let (btn, mut m_text) = make_button(
-        app.state.c_down,
-        ids.frame,
-        options.button_active_colors.c_down,
-        options.button_inactive_colors.c_down,
-        options.background_color,
-        options.display_labels,
-        options.colored_rims,
-    );
-
-    btn.x_y_relative_to(ids.c_left_btn, 0., -48.)
-        .set(ids.c_down_btn, ui);
-
-    if let Some(text_color) = m_text.take() {
-        conrod_core::widget::Text::new("CD")
-            .color(text_color)
-            .mid_top_with_margin_on(ids.c_down_btn, btn_label_margin)
-            .set(ids.c_down_label, ui);
+    if let Some(config_error) = &app.config_error {
+        widget::Text::new(config_error)
+            .color(conrod_core::color::LIGHT_RED)
+            .font_size(scale_font_size(ui.theme().font_size_small, scale))
+            .w(win_w - 20.)
+            .wrap_by_word()
+            .mid_bottom_with_margin_on(ids.frame, 10.)
+            .floating(true)
+            .set(ids.config_error, ui);
     }
 
-    if !options.is_r1_b0xx {
-        let (btn, mut m_text) = make_button(
-            app.state.mod_ls,
-            ids.frame,
-            options.button_active_colors.mod_ls,
-            options.button_inactive_colors.mod_ls,
-            options.background_color,
-            options.display_labels,
-            options.colored_rims,
-        );
+    fps_counter(ui, ids, app);
+}
 
-        btn.x_y_relative_to(ids.y_btn, 43., -5.)
-            .set(ids.mod_ls_btn, ui);
+fn scale_font_size(size: conrod_core::FontSize, scale: f64) -> conrod_core::FontSize {
+    ((size as f64 * scale).round() as conrod_core::FontSize).max(1)
+}
 
-        if let Some(text_color) = m_text.take() {
-            conrod_core::widget::Text::new("LS")
-                .color(text_color)
-                .mid_top_with_margin_on(ids.mod_ls_btn, btn_label_margin)
-                .set(ids.mod_ls_label, ui);
-        }
+/// Returns the custom label font if it has glyphs for the whole label.
+/// Returning `None` falls back on the embedded font of the theme.
+fn label_font(
+    ui: &conrod_core::UiCell,
+    app: &ViewerApp,
+    label: &str,
+) -> Option<conrod_core::text::font::Id> {
+    let font_id = app.label_font?;
+    let font = ui.fonts.get(font_id)?;
+    label
+        .chars()
+        .all(|c| font.glyph(c).id() != rusttype::GlyphId(0))
+        .then_some(font_id)
+}
 
-        let (btn, mut m_text) = make_button(
-            app.state.mod_ms,
-            ids.frame,
-            options.button_active_colors.mod_ms,
-            options.button_inactive_colors.mod_ms,
-            options.background_color,
-            options.display_labels,
-            options.colored_rims,
-        );
+/// Draws the latency histograms in the top right corner, along with their percentiles
+fn latency_histograms(ui: &mut conrod_core::UiCell, ids: &Ids, app: &ViewerApp, scale: f64) {
+    use conrod_core::{Colorable, Positionable, Sizeable, Widget, color, widget};
 
-        btn.x_y_relative_to(ids.mod_ls_btn, 40., -17.)
-            .set(ids.mod_ms_btn, ui);
+    let font_size = scale_font_size(ui.theme().font_size_small, scale);
+    let margin = 8. * scale;
+    let panel_w = 300. * scale;
+    let histogram_h = 40. * scale;
+    // Room for up to 3 lines of percentiles
+    let title_h = font_size as f64 * 3.6;
+    let section_h = title_h + histogram_h + margin;
+    let panel_h = LATENCY_HISTOGRAMS as f64 * section_h + margin;
 
-        if let Some(text_color) = m_text.take() {
-            conrod_core::widget::Text::new("MS")
-                .color(text_color)
-                .mid_top_with_margin_on(ids.mod_ms_btn, btn_label_margin)
-                .set(ids.mod_ms_label, ui);
+    widget::Rectangle::fill_with([panel_w, panel_h], color::BLACK.with_alpha(0.7))
+        .top_right_with_margin_on(ids.frame, 10.)
+        .graphics_for(ids.frame)
+        .set(ids.latency_panel, ui);
+
+    let latency = &app.latency;
+    let jitter = latency
+        .report_interval
+        .std_dev()
+        .map(|jitter| format!(" (jitter {})", format_duration(jitter)))
+        .unwrap_or_default();
+    let histograms = [
+        ("Latency".to_string(), &latency.latency),
+        (format!("Report interval{jitter}"), &latency.report_interval),
+    ];
+
+    let bar_w = (panel_w - 2. * margin) / LATENCY_BUCKETS as f64;
+    for (i, (name, samples)) in histograms.into_iter().enumerate() {
+        let top = i as f64 * section_h + margin;
+        widget::Text::new(&format!("{name}: {}", samples.summary()))
+            .color(color::WHITE)
+            .font_size(font_size)
+            .w(panel_w - 2. * margin)
+            .wrap_by_word()
+            .top_left_with_margins_on(ids.latency_panel, top, margin)
+            .set(ids.latency_titles[i], ui);
+
+        // Scaled on the slowest samples, so that a few outliers don't squash the histogram
+        let max = samples
+            .percentiles([99.])
+            .map_or(std::time::Duration::ZERO, |[p99]| p99.mul_f64(1.25))
+            .max(std::time::Duration::from_millis(1));
+        let histogram = samples.histogram(LATENCY_BUCKETS, max);
+        let highest = histogram.iter().copied().max().unwrap_or_default().max(1);
+        let bottom = panel_h - (top + title_h + histogram_h);
+        for (bucket, count) in histogram.into_iter().enumerate() {
+            let bar_h = (histogram_h * count as f64 / highest as f64).max(1.);
+            widget::Rectangle::fill_with([bar_w - 1., bar_h], color::LIGHT_BLUE)
+                .bottom_left_with_margins_on(
+                    ids.latency_panel,
+                    bottom,
+                    margin + bucket as f64 * bar_w,
+                )
+                .graphics_for(ids.latency_panel)
+                .set(ids.latency_bars[i * LATENCY_BUCKETS + bucket], ui);
         }
     }
-
-    fps_counter(ui, ids, app);
 }
 
 #[cfg(not(feature = "fps"))]
@@ -479,49 +327,128 @@ fn fps_counter(ui: &mut conrod_core::UiCell, ids: &Ids, app: &mut ViewerApp) {
         .set(ids.fps_counter, ui);
 }
 
+/// How the shape of a button is drawn
+#[derive(Debug, Clone, Copy)]
+enum ShapeStyle {
+    Fill(conrod_core::Color),
+    /// Color and thickness of the outline
+    Outline(conrod_core::Color, f64),
+}
+
+/// Shape of a button, scaled to the window
+#[derive(Debug, Clone, Copy)]
+struct ShapeGeometry<'a> {
+    shape: &'a ButtonShape,
+    dim: conrod_core::Dimensions,
+    corner_radius: f64,
+    /// Center of the shape
+    xy: conrod_core::Point,
+}
+
+impl ShapeGeometry<'_> {
+    /// Returns the shape shrunk by `amount` on each axis, or grown if it's negative
+    fn inset(self, amount: f64) -> Self {
+        Self {
+            dim: [self.dim[0] - amount, self.dim[1] - amount],
+            corner_radius: (self.corner_radius - amount / 2.).max(0.),
+            ..self
+        }
+    }
+
+    fn set(
+        self,
+        ui: &mut conrod_core::UiCell,
+        id: conrod_core::widget::Id,
+        parent: conrod_core::widget::Id,
+        style: ShapeStyle,
+    ) {
+        use conrod_core::{Positionable, Widget, widget};
+
+        let [x, y] = self.xy;
+        let line = |color, thickness| {
+            widget::primitive::line::Style::solid()
+                .color(color)
+                .thickness(thickness)
+        };
+
+        let points = self
+            .shape
+            .points(self.dim[0], self.dim[1], self.corner_radius);
+        match (points, style) {
+            (None, ShapeStyle::Fill(color)) => widget::Oval::fill_with(self.dim, color)
+                .x_y(x, y)
+                .parent(parent)
+                .graphics_for(parent)
+                .set(id, ui),
+            (None, ShapeStyle::Outline(color, thickness)) => {
+                widget::Oval::outline_styled(self.dim, line(color, thickness))
+                    .x_y(x, y)
+                    .parent(parent)
+                    .graphics_for(parent)
+                    .set(id, ui)
+            }
+            (Some(points), ShapeStyle::Fill(color)) => {
+                widget::Polygon::centred_fill_with(points, color)
+                    .x_y(x, y)
+                    .parent(parent)
+                    .graphics_for(parent)
+                    .set(id, ui)
+            }
+            (Some(points), ShapeStyle::Outline(color, thickness)) => {
+                widget::Polygon::centred_outline_styled(points, line(color, thickness))
+                    .x_y(x, y)
+                    .parent(parent)
+                    .graphics_for(parent)
+                    .set(id, ui)
+            }
+        }
+    }
+}
+
+/// Returns how a button is drawn, along with the color of its label if labels are displayed.
+/// `is_tapped` is true for buttons released before `min_press_duration`,
+/// and `fade_out` is the progress of released buttons back to their inactive color.
 fn make_button(
     pressed: bool,
-    parent: conrod_core::widget::Id,
-    active_color: crate::colors::ViewerColor,
-    inactive_color: crate::colors::ViewerColor,
-    background_color: crate::colors::ViewerColor,
-    display_labels: bool,
-    colored_rims: bool,
-) -> (
-    conrod_core::widget::Oval<conrod_core::widget::primitive::shape::oval::Full>,
-    Option<conrod_core::Color>,
-) {
-    use conrod_core::{Sizeable, Widget, widget};
-
-    let color = if pressed {
-        active_color
-    } else if colored_rims {
-        background_color
+    is_tapped: bool,
+    fade_out: Option<f32>,
+    button: B0xxButton,
+    options: &ViewerOptions,
+    rim_thickness: f64,
+) -> (ShapeStyle, Option<conrod_core::Color>) {
+    let inactive_color = options.button_inactive_colors.get(button);
+    let colored_rims = options.colored_rims;
+    let active_color = options.button_active_colors.get(button);
+    let pressed_color = match options.tap_color {
+        Some(tap_color) if is_tapped => tap_color,
+        _ => active_color,
+    };
+    let resting_color = if colored_rims {
+        options.background_color
     } else {
         inactive_color
     };
-    let text_color = if display_labels {
+    let color = match fade_out {
+        _ if pressed => pressed_color,
+        Some(t) => pressed_color.lerp(resting_color, t),
+        None => resting_color,
+    };
+    let text_color = if options.display_labels {
         let tmp: conrod_core::Color = color.into();
-        Some(tmp.plain_contrast())
+        Some(
+            options
+                .label_color
+                .map_or_else(|| tmp.plain_contrast(), Into::into),
+        )
     } else {
         None
     };
 
-    let mut widget = if colored_rims && !pressed {
-        widget::Circle::outline_styled(
-            BTN_RADIUS,
-            widget::primitive::line::Style::solid()
-                .color(inactive_color.into())
-                .thickness(2.),
-        )
+    let style = if colored_rims && !pressed && fade_out.is_none() {
+        ShapeStyle::Outline(inactive_color.into(), rim_thickness)
     } else {
-        widget::Circle::fill_with(BTN_RADIUS, color.into())
+        ShapeStyle::Fill(color.into())
     };
 
-    widget = widget
-        .parent(parent)
-        .graphics_for(parent)
-        .w_h(BTN_RADIUS, BTN_RADIUS);
-
-    (widget, text_color)
+    (style, text_color)
 }
@@ -0,0 +1,121 @@
+use crate::{b0xx_state::B0xxButton, config::ViewerOptions, error::ViewerResult};
+use conrod_core::image;
+
+pub type ImageMap = image::Map<glium::texture::SrgbTexture2d>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ButtonImages {
+    pub pressed: Option<image::Id>,
+    pub unpressed: Option<image::Id>,
+}
+
+/// Skin images uploaded to the GPU, ready to be drawn
+#[derive(Debug, Clone, Default)]
+pub struct SkinImages {
+    pub background: Option<image::Id>,
+    buttons: std::collections::HashMap<B0xxButton, ButtonImages>,
+}
+
+impl SkinImages {
+    /// Loads all the images of the configured skin in the image map.
+    /// Images that can't be loaded are logged and skipped.
+    pub fn load(
+        options: &ViewerOptions,
+        display: &glium::Display,
+        image_map: &mut ImageMap,
+    ) -> Self {
+        let mut loader = ImageLoader {
+            options,
+            display,
+            image_map,
+            loaded: Default::default(),
+        };
+
+        let skin = &options.skin;
+        let background = skin
+            .background
+            .as_deref()
+            .and_then(|path| loader.load(path));
+
+        let buttons = B0xxButton::ALL
+            .iter()
+            .map(|&button| {
+                let button_skin = skin.button(button);
+                let images = ButtonImages {
+                    pressed: button_skin
+                        .pressed
+                        .as_deref()
+                        .and_then(|path| loader.load(path)),
+                    unpressed: button_skin
+                        .unpressed
+                        .as_deref()
+                        .and_then(|path| loader.load(path)),
+                };
+                (button, images)
+            })
+            .collect();
+
+        Self {
+            background,
+            buttons,
+        }
+    }
+
+    pub fn button(&self, button: B0xxButton, pressed: bool) -> Option<image::Id> {
+        let images = self.buttons.get(&button)?;
+        if pressed {
+            images.pressed
+        } else {
+            images.unpressed
+        }
+    }
+}
+
+struct ImageLoader<'a> {
+    options: &'a ViewerOptions,
+    display: &'a glium::Display,
+    image_map: &'a mut ImageMap,
+    /// Images shared by several buttons are only uploaded once
+    loaded: std::collections::HashMap<std::path::PathBuf, image::Id>,
+}
+
+impl ImageLoader<'_> {
+    fn load(&mut self, path: &std::path::Path) -> Option<image::Id> {
+        let path = self.options.resolve_path(path);
+
+        if let Some(id) = self.loaded.get(&path) {
+            return Some(*id);
+        }
+
+        match load_texture(self.display, &path) {
+            Ok(texture) => {
+                let id = self.image_map.insert(texture);
+                log::debug!("Loaded skin image {}", path.display());
+                self.loaded.insert(path, id);
+                Some(id)
+            }
+            Err(e) => {
+                log::error!("{e}");
+                None
+            }
+        }
+    }
+}
+
+fn load_texture(
+    display: &glium::Display,
+    path: &std::path::Path,
+) -> ViewerResult<glium::texture::SrgbTexture2d> {
+    use anyhow::Context as _;
+
+    let image = ::image::open(path)
+        .with_context(|| format!("Could not load skin image {}", path.display()))?
+        .to_rgba8();
+    let dimensions = image.dimensions();
+    let raw_image =
+        glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dimensions);
+
+    glium::texture::SrgbTexture2d::new(display, raw_image).map_err(|e| {
+        anyhow::anyhow!("Could not upload skin image {}: {e:?}", path.display()).into()
+    })
+}
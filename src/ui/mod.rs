@@ -1,10 +1,19 @@
 mod app;
 mod gui;
+mod images;
 mod support;
 
-use self::{app::*, support::*};
+use self::{app::*, images::*, support::*};
 
-use crate::{config::ViewerOptions, serial_probe::*};
+use crate::{
+    ViewerResult,
+    b0xx_state::B0xxButton,
+    benchmark::{Benchmark, CpuTimer},
+    config::ViewerOptions,
+    fake_inputs::FakeInputs,
+    latency::LatencyMonitor,
+    serial_probe::*,
+};
 
 use conrod_core::widget_ids;
 use conrod_glium::Renderer;
@@ -19,73 +28,86 @@ use conrod_winit::{
 
 conrod_winit::v023_conversion_fns!();
 
+pub use self::support::{WIN_H, WIN_W};
+
 widget_ids! {
     pub struct Ids {
         frame,
-        reconnect_bg,
-        reconnect_label,
-        start_btn,
-        y_btn,
-        x_btn,
-        b_btn,
-        a_btn,
-        l_btn,
-        r_btn,
-        z_btn,
-        up_btn,
-        down_btn,
-        right_btn,
-        left_btn,
-        mod_x_btn,
-        mod_y_btn,
-        mod_ls_btn,
-        mod_ms_btn,
-        c_left_btn,
-        c_right_btn,
-        c_up_btn,
-        c_down_btn,
-        start_label,
-        y_label,
-        x_label,
-        b_label,
-        a_label,
-        l_label,
-        r_label,
-        z_label,
-        up_label,
-        down_label,
-        right_label,
-        left_label,
-        mod_x_label,
-        mod_y_label,
-        mod_ls_label,
-        mod_ms_label,
-        c_left_label,
-        c_right_label,
-        c_up_label,
-        c_down_label,
+        background,
+        status_bg,
+        status_label,
+        config_error,
+        connection_info,
+        latency_panel,
+        latency_titles[],
+        latency_bars[],
+        buttons[],
+        button_images[],
+        button_glows[],
+        button_shadows[],
+        button_rims[],
+        labels[],
         fps_counter,
     }
 }
 
-pub fn start_gui(mut rx: crossbeam_channel::Receiver<B0xxMessage>, options: ViewerOptions) {
+/// Events waking the event loop up, sent by the other threads
+#[derive(Debug)]
+enum ViewerEvent {
+    /// The serial thread left messages in the mailbox
+    Mailbox,
+    /// The configuration files changed
+    ConfigReloaded(Box<ViewerResult<ViewerOptions>>),
+}
+
+/// Interval between two frames while animating
+const ANIMATION_FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_micros(16_667);
+
+pub fn start_gui(mut options: ViewerOptions, fake_inputs: Option<FakeInputs>) -> ViewerResult<()> {
     // Build the window.
-    let mut events_loop = glium::glutin::event_loop::EventLoop::new();
+    let mut events_loop = glium::glutin::event_loop::EventLoop::<ViewerEvent>::with_user_event();
+
+    let (win_w, win_h) = options.initial_window_size();
+    let aspect_ratio = win_w / win_h;
+
+    let mut title = WIN_TITLE.to_string();
+    if options.fake_inputs.is_some() || options.benchmark.is_some() {
+        title.push_str(FAKE_INPUTS_TITLE_SUFFIX);
+    }
 
     let window = glium::glutin::window::WindowBuilder::new()
         .with_decorations(!options.chromeless)
-        .with_title(WIN_TITLE)
-        .with_resizable(false)
-        .with_inner_size::<glium::glutin::dpi::LogicalSize<u32>>((WIN_W, WIN_H).into());
+        .with_title(title)
+        .with_resizable(true)
+        .with_transparent(options.transparent)
+        .with_min_inner_size(glium::glutin::dpi::LogicalSize::new(
+            WIN_W as f64 * MIN_LAYOUT_SCALE,
+            WIN_H as f64 * MIN_LAYOUT_SCALE,
+        ))
+        .with_inner_size(glium::glutin::dpi::LogicalSize::new(win_w, win_h));
+
+    let msaa = if crate::config::is_valid_msaa(options.msaa) {
+        options.msaa
+    } else {
+        crate::config::DEFAULT_MSAA
+    };
 
+    // Benchmarks draw as many frames as they can
     let context = glium::glutin::ContextBuilder::new()
-        .with_vsync(cfg!(not(feature = "benchmark")))
+        .with_vsync(options.vsync && options.benchmark.is_none())
         .with_gl_robustness(if cfg!(not(debug_assertions)) {
             glium::glutin::Robustness::NoError
         } else {
             glium::glutin::Robustness::TryRobustLoseContextOnReset
         })
-        .with_multisampling(4);
+        .with_multisampling(msaa);
+
+    // Transparent windows need a framebuffer with an alpha channel to be composited
+    let context = if options.transparent {
+        context.with_pixel_format(24, 8)
+    } else {
+        context
+    };
 
     let display = glium::Display::new(window, context, &events_loop).unwrap();
     let display = GliumDisplayWinitWrapper(display);
@@ -93,7 +115,7 @@ pub fn start_gui(mut rx: crossbeam_channel::Receiver<B0xxMessage>, options: View
     let mut scale_factor = display.0.gl_window().window().scale_factor();
 
     // Construct our `Ui`.
-    let mut ui = conrod_core::UiBuilder::new([WIN_W as f64, WIN_H as f64])
+    let mut ui = conrod_core::UiBuilder::new([win_w, win_h])
         .theme(gui::theme())
         .build();
 
@@ -105,134 +127,508 @@ pub fn start_gui(mut rx: crossbeam_channel::Receiver<B0xxMessage>, options: View
 
     ui.theme.font_id = Some(alata_font);
 
-    let ids = Ids::new(ui.widget_id_generator());
+    let mut label_fonts = std::collections::HashMap::new();
+    let label_font = load_label_font(&options, &mut ui, &mut label_fonts);
+
+    let mut ids = Ids::new(ui.widget_id_generator());
+    ids.buttons
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.button_images
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.button_glows
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.button_shadows
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.button_rims
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.labels
+        .resize(B0xxButton::ALL.len(), &mut ui.widget_id_generator());
+    ids.latency_titles
+        .resize(gui::LATENCY_HISTOGRAMS, &mut ui.widget_id_generator());
+    ids.latency_bars.resize(
+        gui::LATENCY_HISTOGRAMS * gui::LATENCY_BUCKETS,
+        &mut ui.widget_id_generator(),
+    );
 
-    let image_map: conrod_core::image::Map<glium::texture::CompressedSrgbTexture2d> =
-        conrod_core::image::Map::new();
+    let mut image_map = ImageMap::new();
 
-    let mut app = ViewerApp::default();
+    let mut app = ViewerApp {
+        skin: SkinImages::load(&options, &display.0, &mut image_map),
+        label_font,
+        profile: options.profile.clone(),
+        config_error: options.config_error.clone(),
+        show_connection_info: options.connection_info,
+        show_latency_histogram: options.latency_histogram,
+        latency: if options.measure_latency.is_some() {
+            LatencyMonitor::unbounded()
+        } else {
+            LatencyMonitor::live()
+        },
+        ..Default::default()
+    };
+
+    // Options with the current profile applied
+    let mut profile_options = options.with_profile(app.profile.as_deref());
+    app.label_set = profile_options.label_set;
+    let mut crossfade = std::time::Duration::from_millis(options.profile_crossfade);
+
+    // Configuration changes wake the event loop up
+    let proxy = events_loop.create_proxy();
+    options.watch(move |reloaded| {
+        proxy
+            .send_event(ViewerEvent::ConfigReloaded(Box::new(reloaded)))
+            .is_ok()
+    });
+
+    // The serial thread wakes the event loop up whenever it leaves messages in the mailbox
+    let proxy = events_loop.create_proxy();
+    let mailbox = crate::serial_probe::connect(
+        options.custom_tty.clone(),
+        fake_inputs,
+        Box::new(move || proxy.send_event(ViewerEvent::Mailbox).is_ok()),
+    )?;
+    log::info!("Serial probe up and running");
+
+    let measure_until = options.measure_latency.map(|seconds| {
+        log::info!("Measuring the latency for {seconds}s");
+        std::time::Instant::now() + std::time::Duration::from_secs(seconds)
+    });
+
+    let mut benchmark = options.benchmark.map(|seconds| {
+        log::info!("Benchmarking for {seconds}s");
+        Benchmark::new(std::time::Duration::from_secs(seconds))
+    });
 
     let mut renderer = Renderer::new(&display).unwrap();
+    let mut clear = clear_color(&profile_options);
+    // CPU time spent in `render_gui` for the frame about to be drawn, if it was built again
+    let mut render_gui_time = None;
+    let mut last_frame_at = None;
 
-    let (glutin_tx, glutin_rx) = crossbeam_channel::bounded::<()>(1);
+    use glium::glutin::event_loop::ControlFlow;
+    use glium::glutin::platform::desktop::EventLoopExtDesktop as _;
+    events_loop.run_return(|event, _, control_flow| {
+        match event {
+            glium::glutin::event::Event::UserEvent(ViewerEvent::Mailbox) => {
+                let (messages, reports) = mailbox.take();
+                if let Some(benchmark) = benchmark.as_mut() {
+                    benchmark.woken_up(reports.as_ref().map_or(0, |reports| reports.count));
+                }
 
-    'main: loop {
-        // Reconnect to the device if needed
-        if app.status == ViewerAppStatus::NeedsReconnection {
-            app.status = ViewerAppStatus::Reconnecting;
-            log::debug!("Trying to reconnect...");
-            drop(rx);
-            rx = reconnect(&options.custom_tty);
-            log::debug!("Reconnected successfully!");
-        }
+                for message in messages {
+                    match message {
+                        B0xxMessage::Connected(device) => {
+                            log::info!("Connected to the B0XX on port {}", device.port_name);
+                            app.connection = Some(ConnectionInfo {
+                                device,
+                                stats: Default::default(),
+                            });
+                            ui.needs_redraw();
+                        }
+                        B0xxMessage::Stats(stats) => {
+                            if let Some(connection) = app.connection.as_mut() {
+                                connection.stats = stats;
+                            }
+
+                            // Keep the uptime and the histograms up to date
+                            if !(options.low_power && benchmark.is_none())
+                                && (app.show_connection_info || app.show_latency_histogram)
+                            {
+                                ui.needs_redraw();
+                            }
+                        }
+                        B0xxMessage::Waiting(status) => {
+                            // Keep the retry countdown up to date
+                            let message = app.status.overlay_message();
+                            app.status = ViewerAppStatus::Waiting(status);
+                            if app.status.overlay_message() != message || app.connection.is_some() {
+                                ui.needs_redraw();
+                            }
+                            app.connection = None;
+                            app.latency.disconnected();
+                        }
+                    }
+                }
 
-        let mut maybe_state = match rx.iter().next() {
-            Some(message) => match message {
-                B0xxMessage::State(state) => {
+                // Redraw our window contents only and only if the state of inputs have changed
+                if let Some(reports) = reports {
                     app.status.set_running();
-                    Some(state)
+                    for read_at in reports.read_times {
+                        app.latency.report_read(read_at);
+                    }
+                    if app.update_state(reports.state, reports.pressed) {
+                        if let Some(changed_at) = reports.changed_at {
+                            app.latency.state_changed(changed_at);
+                        }
+                        ui.needs_redraw();
+                    }
+                }
+            }
+            // Apply configuration changes, keeping the previous configuration on errors
+            glium::glutin::event::Event::UserEvent(ViewerEvent::ConfigReloaded(reloaded)) => {
+                match *reloaded {
+                    Ok(new_options) if new_options.config_error.is_some() => {
+                        app.config_error = new_options.config_error;
+                    }
+                    Ok(new_options) => {
+                        log::info!("Configuration reloaded");
+                        log_startup_settings_changes(&options, &new_options);
+
+                        if new_options.font != options.font {
+                            app.label_font =
+                                load_label_font(&new_options, &mut ui, &mut label_fonts);
+                        }
+
+                        if new_options.skin != options.skin {
+                            image_map = ImageMap::new();
+                            app.skin = SkinImages::load(&new_options, &display.0, &mut image_map);
+                        }
+
+                        if !app
+                            .profile
+                            .as_ref()
+                            .is_some_and(|profile| new_options.profiles.contains_key(profile))
+                        {
+                            app.profile.clone_from(&new_options.profile);
+                        }
+
+                        options = new_options;
+                        profile_options = options.with_profile(app.profile.as_deref());
+                        app.label_set = profile_options.label_set;
+                        crossfade = std::time::Duration::from_millis(options.profile_crossfade);
+                        app.config_error = None;
+                    }
+                    Err(e) => {
+                        log::error!("Could not reload the configuration: {e}");
+                        app.config_error = Some(e.to_string());
+                    }
+                }
+
+                ui.needs_redraw();
+            }
+            glium::glutin::event::Event::WindowEvent { event, .. } => match event {
+                // Exit the program upon pressing `Escape`.
+                glium::glutin::event::WindowEvent::CloseRequested
+                | glium::glutin::event::WindowEvent::KeyboardInput {
+                    input:
+                        glium::glutin::event::KeyboardInput {
+                            virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::Escape),
+                            ..
+                        },
+                    ..
+                } => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                // Cycle through the label sets upon pressing `L`
+                glium::glutin::event::WindowEvent::KeyboardInput {
+                    input:
+                        glium::glutin::event::KeyboardInput {
+                            virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::L),
+                            state: glium::glutin::event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    app.label_set = app.label_set.next();
+                    log::debug!("Switched to label set {:?}", app.label_set);
+                    ui.needs_redraw();
                 }
-                B0xxMessage::Error(e) => {
-                    log::error!("{}", e);
-                    app.status = ViewerAppStatus::NeedsReconnection;
-                    None
+                // Toggle the connection info upon pressing `I`
+                glium::glutin::event::WindowEvent::KeyboardInput {
+                    input:
+                        glium::glutin::event::KeyboardInput {
+                            virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::I),
+                            state: glium::glutin::event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    app.show_connection_info = !app.show_connection_info;
+                    ui.needs_redraw();
+                }
+                // Toggle the latency histograms upon pressing `H`
+                glium::glutin::event::WindowEvent::KeyboardInput {
+                    input:
+                        glium::glutin::event::KeyboardInput {
+                            virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::H),
+                            state: glium::glutin::event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    app.show_latency_histogram = !app.show_latency_histogram;
+                    ui.needs_redraw();
+                }
+                // Cycle through the profiles upon pressing `P`
+                glium::glutin::event::WindowEvent::KeyboardInput {
+                    input:
+                        glium::glutin::event::KeyboardInput {
+                            virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::P),
+                            state: glium::glutin::event::ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    if let Some(next) = options.next_profile(app.profile.as_deref()) {
+                        log::debug!("Switching to profile {next}");
+                        let next_options = options.with_profile(Some(next));
+                        app.switch_profile(next.to_string(), &profile_options, &next_options);
+                        profile_options = next_options;
+                        ui.needs_redraw();
+                    }
                 }
-                B0xxMessage::Quit => {
-                    break 'main;
+                // If ALT is held, allow the window to be click-dragged
+                glium::glutin::event::WindowEvent::ModifiersChanged(modifiers) => {
+                    if modifiers.contains(ModifiersState::ALT) {
+                        app.is_draggable = true;
+                    } else {
+                        app.is_draggable = false;
+                        app.is_dragged = false;
+                    }
+                }
+                glium::glutin::event::WindowEvent::MouseInput {
+                    button: glium::glutin::event::MouseButton::Left,
+                    state,
+                    ..
+                } if app.is_draggable => {
+                    app.is_dragged = state == glium::glutin::event::ElementState::Pressed;
                 }
-                B0xxMessage::Reconnect => {
-                    app.status = ViewerAppStatus::NeedsReconnection;
-                    None
+                glium::glutin::event::WindowEvent::Resized(physical_size) => {
+                    let mut size = physical_size.to_logical::<f64>(scale_factor);
+                    // Snap the window back to its aspect ratio when requested
+                    if options.lock_aspect_ratio {
+                        let locked_height = size.width / aspect_ratio;
+                        if (locked_height - size.height).abs() >= 1. {
+                            size.height = locked_height;
+                            display.0.gl_window().window().set_inner_size(size);
+                        }
+                    }
+
+                    ui.handle_event(conrod_core::event::Input::Resize(size.width, size.height));
                 }
+                glium::glutin::event::WindowEvent::ScaleFactorChanged {
+                    scale_factor: new_scale_factor,
+                    new_inner_size,
+                } => {
+                    // The layout is computed in logical pixels, so only the
+                    // physical size of the window is affected by DPI changes
+                    scale_factor = new_scale_factor;
+                    let size = new_inner_size.to_logical::<f64>(scale_factor);
+                    ui.handle_event(conrod_core::event::Input::Resize(size.width, size.height));
+                }
+                _ => {}
             },
-            None => None,
-        };
-
-        // Redraw our window contents only and only if the state of inputs have
-        // changed in the current cached report
-        if let Some(new_state) = maybe_state.take() {
-            if app.update_state(new_state) {
-                ui.handle_event(conrod_core::event::Input::Redraw);
+            glium::glutin::event::Event::DeviceEvent {
+                event: glium::glutin::event::DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } if app.is_dragged => {
+                let prev_pos = display
+                    .0
+                    .gl_window()
+                    .window()
+                    .outer_position()
+                    .unwrap_or_else(|_| glium::glutin::dpi::PhysicalPosition::new(0, 0))
+                    .to_logical::<f64>(scale_factor);
+
+                display.0.gl_window().window().set_outer_position(
+                    glium::glutin::dpi::LogicalPosition::new(prev_pos.x + dx, prev_pos.y + dy)
+                        .to_physical::<f64>(scale_factor),
+                );
             }
-        }
+            glium::glutin::event::Event::MainEventsCleared => {
+                let now = std::time::Instant::now();
+                // Benchmarks render as fast as possible, whatever the frame rate cap and the low-power mode
+                let low_power = options.low_power && benchmark.is_none();
+                // When the frame rate is capped, the changes wait for the next frame to be drawn
+                let next_frame_at = options
+                    .min_frame_interval()
+                    .filter(|_| benchmark.is_none())
+                    .zip(last_frame_at)
+                    .map(|(interval, last_frame_at)| last_frame_at + interval)
+                    .filter(|next_frame_at| *next_frame_at > now);
+                // The low-power mode only builds the GUI again when something changed
+                let is_idle = low_power && !ui.has_changed() && !app.was_animating;
 
-        // Window event processing
-        use glium::glutin::platform::desktop::EventLoopExtDesktop as _;
-        events_loop.run_return(|event, _, control_flow| {
-            match event {
-                glium::glutin::event::Event::WindowEvent { event, .. } => match event {
-                    // Exit the program upon pressing `Escape`.
-                    glium::glutin::event::WindowEvent::CloseRequested
-                    | glium::glutin::event::WindowEvent::KeyboardInput {
-                        input:
-                            glium::glutin::event::KeyboardInput {
-                                virtual_keycode: Some(glium::glutin::event::VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => {
-                        let _ = glutin_tx.send(());
-                    }
-                    // If ALT is held, allow the window to be click-dragged
-                    glium::glutin::event::WindowEvent::ModifiersChanged(modifiers) => {
-                        if modifiers.contains(ModifiersState::ALT) {
-                            app.is_draggable = true;
-                        } else {
-                            app.is_draggable = false;
-                            app.is_dragged = false;
+                if next_frame_at.is_none() && !is_idle {
+                    // Blend the colors of the previous profile in while crossfading
+                    let crossfade = if low_power {
+                        std::time::Duration::ZERO
+                    } else {
+                        crossfade
+                    };
+                    let blended_options;
+                    let frame_options = match app.profile_transition_progress(crossfade) {
+                        Some((from, t)) => {
+                            blended_options = from.blend(&profile_options, t);
+                            ui.needs_redraw();
+                            &blended_options
                         }
+                        None => &profile_options,
+                    };
+                    clear = clear_color(frame_options);
+
+                    // Animate the presses, drawing one more frame once they're over
+                    let is_animating = app.is_animating(frame_options, now);
+                    if is_animating || app.was_animating {
+                        ui.needs_redraw();
                     }
-                    glium::glutin::event::WindowEvent::MouseInput {
-                        button: glium::glutin::event::MouseButton::Left,
-                        state,
-                        ..
-                    } if app.is_draggable => {
-                        app.is_dragged = state == glium::glutin::event::ElementState::Pressed;
+                    app.was_animating = is_animating;
+
+                    // Instantiate the b0xx viewer GUI
+                    let render_timer = CpuTimer::start();
+                    gui::render_gui(&mut ui.set_widgets(), &ids, &mut app, frame_options);
+
+                    if ui.has_changed() {
+                        render_gui_time = Some(render_timer.elapsed());
+                        display.0.gl_window().window().request_redraw();
                     }
-                    glium::glutin::event::WindowEvent::ScaleFactorChanged {
-                        scale_factor: new_scale_factor,
-                        ..
-                    } => {
-                        scale_factor = new_scale_factor;
+                }
+
+                if measure_until.is_some_and(|until| std::time::Instant::now() >= until) {
+                    app.latency.print();
+                    *control_flow = ControlFlow::Exit;
+                }
+
+                if let Some(benchmark) = benchmark.as_ref().filter(|benchmark| benchmark.is_over())
+                {
+                    let device = app
+                        .connection
+                        .as_ref()
+                        .map(|connection| connection.device.port_name.clone());
+                    let report = benchmark.report(device, &app.latency);
+                    report.print();
+                    match report.write(&options.benchmark_report) {
+                        Ok(()) => log::info!(
+                            "Benchmark report written to {}",
+                            options.benchmark_report.display()
+                        ),
+                        Err(e) => log::error!(
+                            "Could not write the benchmark report to {}: {e}",
+                            options.benchmark_report.display()
+                        ),
                     }
-                    _ => {}
-                },
-                glium::glutin::event::Event::DeviceEvent {
-                    event: glium::glutin::event::DeviceEvent::MouseMotion { delta: (dx, dy) },
-                    ..
-                } if app.is_dragged => {
-                    let prev_pos = display
-                        .0
-                        .gl_window()
-                        .window()
-                        .outer_position()
-                        .unwrap_or_else(|_| glium::glutin::dpi::PhysicalPosition::new(0, 0))
-                        .to_logical::<f64>(scale_factor);
-
-                    display.0.gl_window().window().set_outer_position(
-                        glium::glutin::dpi::LogicalPosition::new(prev_pos.x + dx, prev_pos.y + dy)
-                            .to_physical::<f64>(scale_factor),
-                    );
+                    *control_flow = ControlFlow::Exit;
+                }
+
+                if *control_flow != ControlFlow::Exit {
+                    // Sleep until the next message, unless a frame is pending,
+                    // or a crossfade, an animation or a measurement has to be kept up to date
+                    let pending_frame_at = next_frame_at.filter(|_| ui.has_changed());
+                    let is_animating = app.profile_transition.is_some() || app.was_animating;
+                    let animation_frame_at = is_animating.then(|| {
+                        (now + ANIMATION_FRAME_INTERVAL).max(next_frame_at.unwrap_or(now))
+                    });
+                    let wake_up_at = [
+                        pending_frame_at,
+                        animation_frame_at,
+                        measure_until,
+                        benchmark.as_ref().map(Benchmark::ends_at),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .min();
+                    *control_flow = wake_up_at.map_or(ControlFlow::Wait, ControlFlow::WaitUntil);
                 }
-                _ => {}
             }
-            *control_flow = glium::glutin::event_loop::ControlFlow::Exit;
-        });
+            glium::glutin::event::Event::RedrawRequested(_) => {
+                // Draw the `Ui`. Redraws requested by the system reuse the last drawn primitives
+                let draw_timer = CpuTimer::start();
+                if let Some(primitives) = ui.draw_if_changed() {
+                    renderer.fill(&display.0, primitives, &image_map);
+                }
 
-        if let Ok(_) = glutin_rx.try_recv() {
-            break 'main;
+                let mut frame = display.0.draw();
+                let (r, g, b, a) = clear;
+                frame.clear_color(r, g, b, a);
+                renderer.draw(&display.0, &mut frame, &image_map).unwrap();
+                frame.finish().unwrap();
+
+                let draw_time = draw_timer.elapsed();
+                let presented_at = std::time::Instant::now();
+                last_frame_at = Some(presented_at);
+                app.latency.frame_presented(presented_at);
+                if let Some(benchmark) = benchmark.as_mut() {
+                    benchmark.frame_presented(presented_at, render_gui_time.take(), draw_time);
+                }
+            }
+            _ => {}
         }
+    });
 
-        // Instantiate the b0xx viewer GUI
-        gui::render_gui(&mut ui.set_widgets(), &ids, &mut app, &options);
+    mailbox.close();
+    Ok(())
+}
 
-        // Draw the `Ui`.
-        if let Some(primitives) = ui.draw_if_changed() {
-            renderer.fill(&display.0, primitives, &image_map);
-            let mut frame = display.0.draw();
-            frame.clear_color(0., 0., 0., 1.);
-            renderer.draw(&display.0, &mut frame, &image_map).unwrap();
-            frame.finish().unwrap();
+/// Color the frame is cleared with, premultiplied for the compositor in transparent mode
+fn clear_color(options: &ViewerOptions) -> (f32, f32, f32, f32) {
+    let color: conrod_core::Color = options.background_color.into();
+    let conrod_core::color::Rgba(r, g, b, a) = color.to_rgb();
+    if options.transparent {
+        (r * a, g * a, b * a, a)
+    } else {
+        (r, g, b, 1.)
+    }
+}
+
+/// Warns about the changed settings that are only applied at startup
+fn log_startup_settings_changes(options: &ViewerOptions, new_options: &ViewerOptions) {
+    let changes = [
+        (
+            "window_width",
+            options.window_width != new_options.window_width,
+        ),
+        (
+            "window_height",
+            options.window_height != new_options.window_height,
+        ),
+        ("scale", options.scale != new_options.scale),
+        ("chromeless", options.chromeless != new_options.chromeless),
+        (
+            "transparent",
+            options.transparent != new_options.transparent,
+        ),
+        ("vsync", options.vsync != new_options.vsync),
+        ("msaa", options.msaa != new_options.msaa),
+    ];
+    for (setting, changed) in changes {
+        if changed {
+            log::warn!("`{setting}` is only applied at startup, restart the viewer to apply it");
         }
     }
 }
+
+/// Loads the custom label font of the configuration, if any.
+/// Fonts can't be removed from the UI, so fonts already loaded from the same file are reused on reloads.
+fn load_label_font(
+    options: &ViewerOptions,
+    ui: &mut conrod_core::Ui,
+    loaded: &mut std::collections::HashMap<std::path::PathBuf, conrod_core::text::font::Id>,
+) -> Option<conrod_core::text::font::Id> {
+    let path = options.resolve_path(options.font.as_deref()?);
+    if let Some(&id) = loaded.get(&path) {
+        return Some(id);
+    }
+
+    match load_font(&path) {
+        Ok(font) => {
+            log::debug!("Loaded label font {}", path.display());
+            let id = ui.fonts.insert(font);
+            loaded.insert(path, id);
+            Some(id)
+        }
+        Err(e) => {
+            log::error!("{e}");
+            None
+        }
+    }
+}
+
+fn load_font(path: &std::path::Path) -> crate::ViewerResult<rusttype::Font<'static>> {
+    use anyhow::Context as _;
+
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Could not read font file {}", path.display()))?;
+    let font = rusttype::Font::from_bytes(bytes)
+        .with_context(|| format!("Could not parse font file {}", path.display()))?;
+    Ok(font)
+}
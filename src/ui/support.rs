@@ -1,12 +1,20 @@
+/// Reference window size the button layout has been designed for
 pub const WIN_W: u32 = 600;
 pub const WIN_H: u32 = 300;
 pub const BTN_RADIUS: f64 = 40.;
+/// Smallest scale the window can be shrinked to, relative to the reference size
+pub const MIN_LAYOUT_SCALE: f64 = 0.25;
 
-#[cfg(not(feature = "fake_inputs"))]
-pub const WIN_TITLE: &str = "B0XX Input Viewer - by @OtaK_";
+/// Computes the scale factor of the layout so that it fits a window of the given logical size
+pub fn layout_scale(win_w: f64, win_h: f64) -> f64 {
+    (win_w / WIN_W as f64)
+        .min(win_h / WIN_H as f64)
+        .max(MIN_LAYOUT_SCALE)
+}
 
-#[cfg(feature = "fake_inputs")]
-pub const WIN_TITLE: &str = "B0XX Input Viewer - by @OtaK_ [FAKE INPUTS MODE]";
+pub const WIN_TITLE: &str = "B0XX Input Viewer - by @OtaK_";
+/// Appended to the window title when playing fake inputs
+pub const FAKE_INPUTS_TITLE_SUFFIX: &str = " [FAKE INPUTS MODE]";
 
 pub struct GliumDisplayWinitWrapper(pub glium::Display);
 
@@ -0,0 +1,473 @@
+//! Importer for the themes of the official browser-based input viewer.
+//!
+//! Its look is defined in CSS, either with rules on the buttons (`#a.pressed { fill: #29c7be; }`)
+//! or with custom properties (`--a-pressed-color: #29c7be;`). Colors of the buttons and of the
+//! background are converted, everything else is reported as unsupported.
+
+use crate::b0xx_state::B0xxButton;
+use crate::colors::ViewerColor;
+use crate::migrations::CONFIG_VERSION;
+use crate::{ViewerOptionConfigError, ViewerResult};
+
+/// Setting of the viewer that a CSS declaration maps onto
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThemeTarget {
+    Background,
+    Label,
+    /// Buttons in their pressed state
+    Active(Option<B0xxButton>),
+    /// Buttons in their unpressed state
+    Inactive(Option<B0xxButton>),
+}
+
+impl ThemeTarget {
+    /// Rules for every button apply before the ones of a single button, whatever their order
+    fn specificity(self) -> u8 {
+        match self {
+            Self::Active(Some(_)) | Self::Inactive(Some(_)) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Theme converted to the configuration format
+#[derive(Debug, Default)]
+pub struct ImportedTheme {
+    pub background_color: Option<ViewerColor>,
+    pub label_color: Option<ViewerColor>,
+    pub button_active_colors: std::collections::BTreeMap<B0xxButton, ViewerColor>,
+    pub button_inactive_colors: std::collections::BTreeMap<B0xxButton, ViewerColor>,
+    /// Settings of the theme that can't be represented, with their line
+    pub unsupported: Vec<String>,
+}
+
+impl ImportedTheme {
+    /// Converts the CSS of a web viewer theme
+    pub fn from_css(source: &str) -> Self {
+        let source = strip_comments(source);
+        let mut theme = Self::default();
+        let mut assignments = vec![];
+        let mut variables = std::collections::HashMap::new();
+
+        let rules = parse_rules(&source);
+        for rule in &rules {
+            for declaration in &rule.declarations {
+                if declaration.property.starts_with("--") {
+                    variables.insert(declaration.property.as_str(), declaration.value.as_str());
+                }
+            }
+        }
+
+        for rule in &rules {
+            if rule.selectors.starts_with('@') {
+                theme.report(
+                    rule.line,
+                    format!("`{}` rules are not supported", rule.selectors),
+                );
+                continue;
+            }
+
+            for declaration in &rule.declarations {
+                let value = resolve_variables(&declaration.value, &variables);
+                // Custom properties are named after what they style
+                let is_variable = declaration.property.starts_with("--");
+                let (names, property) = match declaration.property.strip_prefix("--") {
+                    Some(name) => (vec![name], "fill"),
+                    None => (
+                        rule.selectors.split(',').map(str::trim).collect(),
+                        declaration.property.as_str(),
+                    ),
+                };
+
+                for name in names {
+                    match theme_target(name, property, is_variable) {
+                        Ok(Some(target)) => match value.parse::<ViewerColor>() {
+                            Ok(color) => assignments.push((target, color)),
+                            Err(e) => theme.report(declaration.line, e.to_string()),
+                        },
+                        // Custom properties that aren't colors are only used through `var()`
+                        Ok(None) => {}
+                        Err(()) => theme.report(
+                            declaration.line,
+                            format!(
+                                "`{}: {value}` of `{name}` can't be represented",
+                                declaration.property
+                            ),
+                        ),
+                    }
+                }
+            }
+        }
+
+        assignments.sort_by_key(|(target, _)| target.specificity());
+        for (target, color) in assignments {
+            theme.assign(target, color);
+        }
+
+        theme
+    }
+
+    fn report(&mut self, line: usize, message: String) {
+        self.unsupported.push(format!("line {line}: {message}"));
+    }
+
+    fn assign(&mut self, target: ThemeTarget, color: ViewerColor) {
+        let buttons = |button: Option<B0xxButton>| match button {
+            Some(button) => vec![button],
+            None => B0xxButton::ALL.to_vec(),
+        };
+
+        match target {
+            ThemeTarget::Background => self.background_color = Some(color),
+            ThemeTarget::Label => self.label_color = Some(color),
+            ThemeTarget::Active(button) => {
+                for button in buttons(button) {
+                    self.button_active_colors.insert(button, color);
+                }
+            }
+            ThemeTarget::Inactive(button) => {
+                for button in buttons(button) {
+                    self.button_inactive_colors.insert(button, color);
+                }
+            }
+        }
+    }
+
+    /// Serializes the theme as a configuration file
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        let mut table = toml::Table::new();
+        table.insert("version".into(), CONFIG_VERSION.into());
+
+        let color = |color: &ViewerColor| toml::Value::String(color.to_string());
+        if let Some(background_color) = &self.background_color {
+            table.insert("background_color".into(), color(background_color));
+        }
+
+        if let Some(label_color) = &self.label_color {
+            table.insert("label_color".into(), color(label_color));
+        }
+
+        for (key, colors) in [
+            ("button_inactive_colors", &self.button_inactive_colors),
+            ("button_active_colors", &self.button_active_colors),
+        ] {
+            if colors.is_empty() {
+                continue;
+            }
+
+            let mut buttons = toml::Table::new();
+            for (button, button_color) in colors {
+                if let toml::Value::String(name) = toml::Value::try_from(button)? {
+                    buttons.insert(name, color(button_color));
+                }
+            }
+            table.insert(key.into(), buttons.into());
+        }
+
+        toml::to_string_pretty(&table)
+    }
+
+    /// Imports the web viewer theme at `theme_path` into a new configuration file at `config_path`
+    pub fn import(theme_path: &std::path::Path, config_path: &std::path::Path) -> ViewerResult<()> {
+        if config_path.exists() {
+            return Err(anyhow::anyhow!(
+                "{} already exists, provide another path with --config",
+                config_path.display()
+            )
+            .into());
+        }
+
+        let theme = Self::from_css(&std::fs::read_to_string(theme_path)?);
+        for unsupported in &theme.unsupported {
+            log::warn!("{}: {unsupported}", theme_path.display());
+        }
+
+        let toml_output = theme.to_toml().map_err(ViewerOptionConfigError::from)?;
+        if let Some(dir) = config_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(config_path, toml_output)?;
+        log::info!(
+            "Imported {} into {}",
+            theme_path.display(),
+            config_path.display()
+        );
+
+        Ok(())
+    }
+}
+
+struct CssRule {
+    line: usize,
+    selectors: String,
+    declarations: Vec<CssDeclaration>,
+}
+
+struct CssDeclaration {
+    line: usize,
+    property: String,
+    value: String,
+}
+
+/// Replaces the comments with spaces, keeping the line numbers intact
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("*/")
+            .map_or(rest.len(), |end| start + end + 2);
+        output.extend(
+            rest[start..end]
+                .chars()
+                .map(|c| if c == '\n' { '\n' } else { ' ' }),
+        );
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Parses flat CSS rules. Nested blocks, such as the ones of `@media` rules, are flattened.
+fn parse_rules(source: &str) -> Vec<CssRule> {
+    let line_at = |offset: usize| source[..offset].matches('\n').count() + 1;
+    let mut rules = vec![];
+    let mut offset = 0;
+
+    for block in source.split_inclusive('}') {
+        let block_offset = offset;
+        offset += block.len();
+
+        let Some((selectors, body)) = block.trim_end_matches('}').rsplit_once('{') else {
+            continue;
+        };
+
+        let body_offset = block_offset + selectors.len() + 1;
+        let mut declarations = vec![];
+        let mut declaration_offset = body_offset;
+        for declaration in body.split_inclusive(';') {
+            let line =
+                line_at(declaration_offset + declaration.len() - declaration.trim_start().len());
+            declaration_offset += declaration.len();
+
+            let Some((property, value)) = declaration.trim_end_matches(';').split_once(':') else {
+                continue;
+            };
+
+            let value = value.trim().trim_end_matches("!important").trim();
+            declarations.push(CssDeclaration {
+                line,
+                property: property.trim().to_ascii_lowercase(),
+                value: value.to_string(),
+            });
+        }
+
+        // Keep the last selector of blocks that were nested into another one
+        let selectors = selectors.rsplit('{').next().unwrap_or(selectors);
+        let selectors_start = selectors.len() - selectors.trim_start().len();
+        rules.push(CssRule {
+            line: line_at(body_offset - 1 - selectors.len() + selectors_start),
+            selectors: selectors.trim().to_string(),
+            declarations,
+        });
+    }
+
+    rules
+}
+
+/// Replaces the `var(--name)` references of `value` with the value of the custom property
+fn resolve_variables(value: &str, variables: &std::collections::HashMap<&str, &str>) -> String {
+    let mut value = value.to_string();
+    // Bounded to avoid looping on circular references
+    for _ in 0..8 {
+        let Some(start) = value.find("var(") else {
+            break;
+        };
+        let Some(end) = value[start..].find(')').map(|end| start + end) else {
+            break;
+        };
+
+        let reference = &value[start + 4..end];
+        let (name, fallback) = reference
+            .split_once(',')
+            .map_or((reference, None), |(name, fallback)| (name, Some(fallback)));
+        let resolved = variables
+            .get(name.trim())
+            .copied()
+            .or(fallback)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        value.replace_range(start..=end, &resolved);
+    }
+    value
+}
+
+/// Maps a CSS selector or custom property name and one of its properties onto a setting.
+/// Returns `Ok(None)` for custom properties that don't name a setting, `Err` for what can't be represented.
+fn theme_target(name: &str, property: &str, is_variable: bool) -> Result<Option<ThemeTarget>, ()> {
+    let mut pressed = None;
+    let mut is_background = false;
+    let mut is_button = false;
+    let mut is_label = property == "color";
+    let mut identifier = String::new();
+    for token in name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .map(str::to_ascii_lowercase)
+    {
+        match token.as_str() {
+            "pressed" | "active" | "held" | "on" => pressed = Some(true),
+            "unpressed" | "inactive" | "released" | "idle" | "off" => pressed = Some(false),
+            "background" | "bg" | "body" | "html" | "root" | "viewer" => is_background = true,
+            "button" | "buttons" | "btn" | "circle" => is_button = true,
+            "label" | "text" => is_label = true,
+            "" | "color" | "colour" | "fill" => {}
+            token => identifier.push_str(token),
+        }
+    }
+
+    let button = match web_button(&identifier) {
+        Some(button) => Some(button),
+        None if identifier.is_empty() => None,
+        // Custom properties can hold anything, they only matter when referenced by a rule
+        None if is_variable => return Ok(None),
+        None => return Err(()),
+    };
+
+    if !matches!(
+        property,
+        "fill" | "background" | "background-color" | "color"
+    ) {
+        return Err(());
+    }
+
+    Ok(Some(match (button, pressed) {
+        // Labels have the same color on every button
+        (None, _) if is_label => ThemeTarget::Label,
+        (Some(_), _) if is_label => return Err(()),
+        (None, None) if is_background => ThemeTarget::Background,
+        (None, None) if !is_button => return if is_variable { Ok(None) } else { Err(()) },
+        (button, Some(true)) => ThemeTarget::Active(button),
+        (button, _) => ThemeTarget::Inactive(button),
+    }))
+}
+
+/// Maps the identifier of a button of the web viewer onto a B0XX button
+fn web_button(identifier: &str) -> Option<B0xxButton> {
+    Some(match identifier {
+        "start" | "pause" | "st" => B0xxButton::Start,
+        "y" => B0xxButton::Y,
+        "x" => B0xxButton::X,
+        "b" => B0xxButton::B,
+        "a" => B0xxButton::A,
+        "l" | "lt" => B0xxButton::L,
+        "r" | "rt" => B0xxButton::R,
+        "z" => B0xxButton::Z,
+        "up" => B0xxButton::Up,
+        "down" => B0xxButton::Down,
+        "right" => B0xxButton::Right,
+        "left" => B0xxButton::Left,
+        "modx" | "mx" => B0xxButton::ModX,
+        "mody" | "my" => B0xxButton::ModY,
+        "cleft" | "cl" => B0xxButton::CLeft,
+        "cright" | "cr" => B0xxButton::CRight,
+        "cup" | "cu" => B0xxButton::CUp,
+        "cdown" | "cd" => B0xxButton::CDown,
+        "ls" | "lightshield" | "modls" => B0xxButton::ModLs,
+        "ms" | "midshield" | "modms" => B0xxButton::ModMs,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEME: &str = r#"/* Cyan theme
+   for the B0XX web viewer */
+:root {
+  --bg-color: #1a1a2e;
+  --accent: #29c7be;
+  --radius: 4px;
+}
+
+body { background-color: var(--bg-color); }
+
+#a.pressed { fill: #ff0000 !important; }
+.button.pressed { fill: var(--accent); }
+/* #b.pressed { fill: #00ff00; } */
+#a, #b { fill: rgb(40, 40, 40); }
+.button {
+  fill: #333333;
+  stroke: #000;
+}
+.label { color: var(--label-color, white); }
+#x.pressed { fill: none; }
+
+@media (max-width: 600px) {
+  .button { fill: #444444; }
+}
+@font-face { font-family: Alata; }
+"#;
+
+    fn color(
+        colors: &std::collections::BTreeMap<B0xxButton, ViewerColor>,
+        button: B0xxButton,
+    ) -> String {
+        colors[&button].to_string()
+    }
+
+    #[test]
+    fn imports_web_viewer_themes() {
+        let theme = ImportedTheme::from_css(THEME);
+
+        assert_eq!(theme.background_color.unwrap().to_string(), "#1A1A2E");
+        assert_eq!(theme.label_color.unwrap().to_string(), "white");
+
+        // Rules of a single button win over the ones of every button, whatever their order
+        assert_eq!(color(&theme.button_active_colors, B0xxButton::A), "#FF0000");
+        assert_eq!(color(&theme.button_active_colors, B0xxButton::B), "#29C7BE");
+        assert_eq!(color(&theme.button_active_colors, B0xxButton::X), "#29C7BE");
+        assert_eq!(
+            color(&theme.button_inactive_colors, B0xxButton::A),
+            "rgb(40, 40, 40)"
+        );
+        assert_eq!(
+            color(&theme.button_inactive_colors, B0xxButton::B),
+            "rgb(40, 40, 40)"
+        );
+        assert_eq!(
+            color(&theme.button_inactive_colors, B0xxButton::Y),
+            "#444444"
+        );
+
+        assert_eq!(
+            theme.unsupported,
+            [
+                "line 17: `stroke: #000` of `.button` can't be represented",
+                "line 20: Invalid color `none`: unknown color, expected a hex color such as \"#00EBFF\", rgb(), rgba(), hsl(), hsla() or a CSS color name",
+                "line 25: `@font-face` rules are not supported",
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_comments_keeping_the_lines() {
+        assert_eq!(
+            strip_comments("a { /* b\nc */ fill: red; } /* d"),
+            "a {     \n     fill: red; }     "
+        );
+    }
+
+    #[test]
+    fn resolves_variables() {
+        let variables =
+            std::collections::HashMap::from([("--accent", "#29c7be"), ("--loop", "var(--loop)")]);
+
+        assert_eq!(resolve_variables("var(--accent)", &variables), "#29c7be");
+        assert_eq!(resolve_variables("var(--missing, red)", &variables), "red");
+        assert_eq!(resolve_variables("var(--missing)", &variables), "");
+        assert_eq!(resolve_variables("var(--loop)", &variables), "var(--loop)");
+    }
+}